@@ -1,9 +1,11 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{cell::RefCell, collections::HashMap, str::FromStr};
 
+use futures::StreamExt;
 use http::{HeaderName, HeaderValue, Method};
 use module::{
-    http::{Request, Response},
-    Message,
+    forbidden::{self, Forbidden},
+    http::{Phase, Progress, Request, Response, Transfer},
+    Message, PORT_NAME,
 };
 use reqwest::{Body, Client, Url};
 use snafu::Snafu;
@@ -14,13 +16,54 @@ use tracing_web::MakeWebConsoleWriter;
 use wasm_bindgen::prelude::*;
 #[allow(deprecated)]
 use wasm_bindgen::JsStatic;
-use wasm_bindgen_futures::js_sys::Function;
+use wasm_bindgen_futures::js_sys::{Date, Function, Promise};
 use web_extensions::tabs::{self, CreateProperties};
-use web_extensions_sys::Browser;
+use web_extensions_sys::{Browser, Port};
+
+mod rule;
 
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+/// Progress is reported at most once per this many received bytes.
+const PROGRESS_STEP: u64 = 64 * 1024;
+/// A finished transfer no popup attached to is dropped after this long.
+const UNCLAIMED_MILLIS: f64 = 10.0 * 60.0 * 1000.0;
+/// Extension API calls reset the service worker idle timer, which is 30 seconds.
+const KEEP_ALIVE_MILLIS: i32 = 20_000;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setInterval)]
+    fn set_interval(handler: &Function, timeout: i32) -> i32;
+
+    #[wasm_bindgen(js_name = clearInterval)]
+    fn clear_interval(handle: i32);
+
+    #[wasm_bindgen(js_namespace = ["chrome", "runtime"], js_name = getPlatformInfo)]
+    fn get_platform_info() -> Promise;
+
+    /// [`Port`] whose `postMessage` throws once the other end has gone away.
+    type CheckedPort;
+
+    #[wasm_bindgen(catch, method, js_name = postMessage)]
+    fn post_message(this: &CheckedPort, value: &JsValue) -> Result<(), JsValue>;
+}
+
+/// A request in flight, or finished while no popup was connected to receive it.
+struct InFlight {
+    port: Option<Port>,
+    last: Option<Message>,
+    done: bool,
+    /// When it finished with no popup attached, see [`UNCLAIMED_MILLIS`].
+    unclaimed_since: Option<f64>,
+}
+
+thread_local! {
+    static IN_FLIGHT: RefCell<HashMap<String, InFlight>> = RefCell::new(HashMap::new());
+    static KEEP_ALIVE: RefCell<Option<i32>> = const { RefCell::new(None) };
+}
+
 #[wasm_bindgen(start)]
 pub fn start() {
     console_error_panic_hook::set_once();
@@ -44,12 +87,18 @@ pub fn start() {
         .add_listener(on_clicked.as_ref().unchecked_ref());
     on_clicked.forget();
 
-    let on_message: Closure<dyn Fn(JsValue, JsValue, Function) -> bool> = Closure::new(on_message);
+    let on_connect: Closure<dyn Fn(Port)> = Closure::new(on_connect);
     browser()
         .runtime()
-        .on_message()
-        .add_listener(on_message.as_ref().unchecked_ref());
-    on_message.forget();
+        .on_connect()
+        .add_listener(on_connect.as_ref().unchecked_ref());
+    on_connect.forget();
+
+    wasm_bindgen_futures::spawn_local(async {
+        let _ = rule::clear()
+            .await
+            .inspect_err(|e| error!("Failed to clear header rules: {e:?}"));
+    });
 
     info!("background started");
 }
@@ -64,36 +113,158 @@ fn on_clicked() {
     })
 }
 
-fn on_message(request: JsValue, _sender: JsValue, send_response: Function) -> bool {
-    wasm_bindgen_futures::spawn_local(async move {
-        let this = JsValue::NULL;
-        let resp = match route(request).await {
-            Ok(response) => response,
-            Err(e) => {
-                let message: Message = e.into();
-                error!("{message:?}");
-                serde_wasm_bindgen::to_value(&message).unwrap()
+fn on_connect(port: Port) {
+    if PORT_NAME != port.name() {
+        return;
+    }
+
+    let on_message: Closure<dyn Fn(JsValue)> = {
+        let port = port.clone();
+        Closure::new(move |message| {
+            if let Err(e) = route(&port, message) {
+                error!("{e}");
+                post(&port, &e.into());
             }
-        };
+        })
+    };
+    port.on_message()
+        .add_listener(on_message.as_ref().unchecked_ref());
+    on_message.forget();
 
-        if let Err(e) = send_response.call1(&this, &resp) {
-            error!("{e:?}");
-        }
-    });
-    true
+    let on_disconnect: Closure<dyn Fn(Port)> = Closure::new(detach);
+    port.on_disconnect()
+        .add_listener(on_disconnect.as_ref().unchecked_ref());
+    on_disconnect.forget();
 }
 
-async fn route(message: JsValue) -> Result<JsValue, Error> {
+fn route(port: &Port, message: JsValue) -> Result<(), Error> {
     let module::Message { code, value } = serde_wasm_bindgen::from_value(message)?;
     match code.as_str() {
         "http_send" => {
-            let req = serde_json::from_str(&value)?;
-            let resp = send(req).await?;
-            let message: Message = resp.into();
-            Ok(serde_wasm_bindgen::to_value(&message)?)
+            let Transfer { id, request } = serde_json::from_str(&value)?;
+            IN_FLIGHT.with_borrow_mut(|in_flight| {
+                let now = Date::now();
+                in_flight.retain(|_, transfer| {
+                    transfer
+                        .unclaimed_since
+                        .is_none_or(|since| now - since < UNCLAIMED_MILLIS)
+                });
+                in_flight.insert(
+                    id.clone(),
+                    InFlight {
+                        port: Some(port.clone()),
+                        last: None,
+                        done: false,
+                        unclaimed_since: None,
+                    },
+                )
+            });
+            keep_alive();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let message =
+                    match send(request, |progress| emit(&id, progress.into(), false)).await {
+                        Ok(response) => response.into(),
+                        Err(e) => {
+                            error!("{e}");
+                            e.into()
+                        }
+                    };
+                emit(&id, message, true);
+            });
+        }
+        "http_attach" => {
+            let attached = IN_FLIGHT.with_borrow_mut(|in_flight| {
+                let transfer = in_flight.get_mut(&value)?;
+                let last = transfer.last.clone();
+                if transfer.done {
+                    in_flight.remove(&value);
+                } else {
+                    transfer.port = Some(port.clone());
+                }
+                Some(last)
+            });
+            match attached {
+                Some(Some(last)) => post(port, &last),
+                Some(None) => {}
+                None => post(
+                    port,
+                    &Message {
+                        code: "error".to_string(),
+                        value: "The request was lost, the background restarted".to_string(),
+                    },
+                ),
+            }
+        }
+        // Popup pings only keep the service worker awake.
+        "ping" => {}
+        _ => return UnknownMessageSnafu { code }.fail(),
+    }
+    Ok(())
+}
+
+/// Forwards `message` to the popup of transfer `id`, or keeps it until the popup reattaches.
+fn emit(id: &str, message: Message, done: bool) {
+    let port = IN_FLIGHT.with_borrow_mut(|in_flight| {
+        let transfer = in_flight.get_mut(id)?;
+        let port = transfer.port.clone();
+        if done && port.is_some() {
+            in_flight.remove(id);
+        } else {
+            transfer.last = Some(message.clone());
+            transfer.done = done;
+            if done && port.is_none() {
+                transfer.unclaimed_since = Some(Date::now());
+            }
         }
-        _ => unimplemented!(),
+        port
+    });
+
+    if let Some(port) = port {
+        post(&port, &message);
     }
+    if done {
+        keep_alive();
+    }
+}
+
+fn detach(port: Port) {
+    IN_FLIGHT.with_borrow_mut(|in_flight| {
+        for transfer in in_flight.values_mut() {
+            if transfer.port.as_ref() == Some(&port) {
+                transfer.port = None;
+            }
+        }
+    });
+}
+
+fn post(port: &Port, message: &Message) {
+    let _ = serde_wasm_bindgen::to_value(message)
+        .map_err(JsValue::from)
+        .and_then(|message| port.unchecked_ref::<CheckedPort>().post_message(&message))
+        .inspect_err(|e| error!("Failed to post message: {e:?}"));
+}
+
+/// Keeps the service worker alive while any request is still running.
+fn keep_alive() {
+    let running = IN_FLIGHT.with_borrow(|in_flight| in_flight.values().any(|t| !t.done));
+    KEEP_ALIVE.with_borrow_mut(|handle| match (running, *handle) {
+        (true, None) => {
+            let ping: Closure<dyn Fn()> = Closure::new(|| {
+                let _ = get_platform_info();
+            });
+            *handle = Some(set_interval(
+                ping.as_ref().unchecked_ref(),
+                KEEP_ALIVE_MILLIS,
+            ));
+            ping.forget();
+        }
+        (false, Some(h)) => {
+            clear_interval(h);
+            *handle = None;
+        }
+        _ => {}
+    });
 }
 
 async fn send(
@@ -103,31 +274,91 @@ async fn send(
         header,
         body,
     }: Request,
+    progress: impl Fn(Progress),
 ) -> Result<Response, Error> {
+    if forbidden::method(&method) {
+        return ForbiddenMethodSnafu { method }.fail();
+    }
     let method = Method::from_str(&method)?;
     let url = Url::from_str(&uri)?;
-    let mut request = reqwest::Request::new(method, url);
+    let mut request = reqwest::Request::new(method.clone(), url.clone());
+    let mut ruled = Vec::new();
     for (name, value) in header {
-        request
-            .headers_mut()
-            .append(HeaderName::from_str(&name)?, HeaderValue::from_str(&value)?);
+        match forbidden::header(&name) {
+            Some(Forbidden::Rule) => ruled.push((name, value)),
+            Some(Forbidden::Never) => info!("{name} is set by the browser, dropped"),
+            None => {
+                request
+                    .headers_mut()
+                    .append(HeaderName::from_str(&name)?, HeaderValue::from_str(&value)?);
+            }
+        }
     }
     let _ = request.body_mut().insert(Body::from(body));
 
+    let _claim = rule::claim(url.as_str(), method.as_str(), !ruled.is_empty()).await;
+    // A rule Chrome refuses, like one for a URL too long for its regexFilter, only costs the
+    // headers it would have set.
+    let rule = if ruled.is_empty() {
+        None
+    } else {
+        rule::add(url.as_str(), method.as_str(), ruled)
+            .await
+            .inspect_err(|e| error!("Failed to add header rule, sending without it: {e:?}"))
+            .ok()
+    };
+    let result = exchange(request, progress).await;
+    if let Some(id) = rule {
+        let _ = rule::remove(id)
+            .await
+            .inspect_err(|e| error!("Failed to remove header rule: {e:?}"));
+    }
+    result
+}
+
+async fn exchange(
+    request: reqwest::Request,
+    progress: impl Fn(Progress),
+) -> Result<Response, Error> {
+    progress(Progress {
+        phase: Phase::Sending,
+        received: 0,
+        total: None,
+    });
     let done_date = OffsetDateTime::now_local()?;
     let resp = Client::new().execute(request).await?;
     let elapsed_time = OffsetDateTime::now_local()? - done_date;
+    let uri = resp.url().to_string();
     let mut header = HashMap::new();
     for (name, value) in resp.headers() {
         header.insert(name.to_string(), value.as_bytes().to_vec());
     }
 
+    let status = resp.status().as_u16();
+    let total = resp.content_length();
+    let mut body = Vec::new();
+    let mut reported = 0;
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk?);
+        let received = body.len() as u64;
+        if received - reported >= PROGRESS_STEP {
+            reported = received;
+            progress(Progress {
+                phase: Phase::Receiving,
+                received,
+                total,
+            });
+        }
+    }
+
     Ok(Response {
         done_date,
-        status: resp.status().as_u16(),
+        status,
         header,
-        body: resp.bytes().await?.into(),
+        body,
         elapsed_time: elapsed_time.whole_milliseconds() as i32,
+        uri,
     })
 }
 
@@ -142,8 +373,12 @@ pub enum Error {
     Message { source: serde_wasm_bindgen::Error },
     #[snafu(display("Failed to ser/de request: {source}"), context(false))]
     Request { source: serde_json::Error },
+    #[snafu(display("Unknown message: {code}"))]
+    UnknownMessage { code: String },
     #[snafu(display("{source}"), context(false))]
     Method { source: http::method::InvalidMethod },
+    #[snafu(display("Browsers refuse to send {method} requests"))]
+    ForbiddenMethod { method: String },
     #[snafu(display("Invalid url: {source}"), context(false))]
     Url { source: url::ParseError },
     #[snafu(display("{source}"), context(false))]
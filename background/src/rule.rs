@@ -0,0 +1,263 @@
+//! Session `declarativeNetRequest` rules setting the headers fetch drops, see
+//! [`Forbidden::Rule`](module::forbidden::Forbidden::Rule). A rule matches the URL and method
+//! of one request and is removed once its response is in, other requests to them wait meanwhile.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::poll_fn,
+    task::{Poll, Waker},
+};
+
+use futures::future::{FutureExt, LocalBoxFuture, Shared};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{js_sys::Promise, JsFuture};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(catch, js_namespace = ["chrome", "declarativeNetRequest"], js_name = updateSessionRules)]
+    fn update_session_rules(options: &JsValue) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = ["chrome", "declarativeNetRequest"], js_name = getSessionRules)]
+    fn get_session_rules() -> Result<Promise, JsValue>;
+}
+
+/// `chrome.declarativeNetRequest` tab id of requests made outside tabs, like ours.
+const TAB_ID_NONE: i32 = -1;
+/// `RequestMethod` values a rule condition knows, others match as `other`.
+const RULE_METHODS: &[&str] = &[
+    "connect", "delete", "get", "head", "options", "patch", "post", "put",
+];
+
+/// Requests in flight to one [`target`].
+#[derive(Default)]
+struct Target {
+    plain: usize,
+    ruled: bool,
+    waiting: Vec<Waker>,
+}
+
+/// A request in flight, see [`claim`].
+pub struct Claim {
+    key: (String, String),
+    ruled: bool,
+}
+
+thread_local! {
+    static TARGETS: RefCell<HashMap<(String, String), Target>> = RefCell::new(HashMap::new());
+    static NEXT_ID: Cell<u32> = const { Cell::new(1) };
+    /// The one run of [`clear_stale`] of this worker.
+    static CLEARED: Shared<LocalBoxFuture<'static, Result<(), JsValue>>> =
+        clear_stale().boxed_local().shared();
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateRuleOptions {
+    add_rules: Vec<Rule>,
+    remove_rule_ids: Vec<u32>,
+}
+
+#[derive(Serialize)]
+struct Rule {
+    id: u32,
+    priority: u32,
+    action: RuleAction,
+    condition: RuleCondition,
+}
+
+/// A rule as read back, only its id matters.
+#[derive(Deserialize)]
+struct RuleId {
+    id: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleAction {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    request_headers: Vec<ModifyHeaderInfo>,
+}
+
+#[derive(Serialize)]
+struct ModifyHeaderInfo {
+    header: String,
+    operation: &'static str,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleCondition {
+    regex_filter: String,
+    request_methods: Vec<String>,
+    resource_types: Vec<&'static str>,
+    tab_ids: Vec<i32>,
+}
+
+/// Adds a rule setting `headers` on the request `method` `url`, and returns its id.
+pub async fn add(url: &str, method: &str, headers: Vec<(String, String)>) -> Result<u32, JsValue> {
+    // Ids start over with the worker, the rules of the last one have to be gone first.
+    clear().await?;
+    let id = NEXT_ID.get();
+    NEXT_ID.set(id + 1);
+    let (url, method) = target(url, method);
+    let rule = Rule {
+        id,
+        priority: 1,
+        action: RuleAction {
+            kind: "modifyHeaders",
+            request_headers: headers
+                .into_iter()
+                .map(|(header, value)| ModifyHeaderInfo {
+                    header,
+                    operation: "set",
+                    value,
+                })
+                .collect(),
+        },
+        condition: RuleCondition {
+            regex_filter: exact(&url),
+            request_methods: vec![method],
+            resource_types: vec!["xmlhttprequest", "other"],
+            tab_ids: vec![TAB_ID_NONE],
+        },
+    };
+    update(UpdateRuleOptions {
+        add_rules: vec![rule],
+        remove_rule_ids: Vec::new(),
+    })
+    .await?;
+    Ok(id)
+}
+
+/// Waits until the request `method` `url` can be sent without another one's rule applying to
+/// it. A request setting headers by rule, `ruled`, goes alone, the others only wait for such a
+/// one. The returned [`Claim`] is held until the response is in and the rule removed.
+pub async fn claim(url: &str, method: &str, ruled: bool) -> Claim {
+    let key = target(url, method);
+    poll_fn(|cx| {
+        TARGETS.with_borrow_mut(|targets| {
+            let target = targets.entry(key.clone()).or_default();
+            if target.ruled || (ruled && target.plain > 0) {
+                target.waiting.push(cx.waker().clone());
+                return Poll::Pending;
+            }
+            if ruled {
+                target.ruled = true;
+            } else {
+                target.plain += 1;
+            }
+            Poll::Ready(())
+        })
+    })
+    .await;
+    Claim { key, ruled }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        TARGETS.with_borrow_mut(|targets| {
+            let Some(target) = targets.get_mut(&self.key) else {
+                return;
+            };
+            if self.ruled {
+                target.ruled = false;
+            } else {
+                target.plain -= 1;
+            }
+            target.waiting.drain(..).for_each(Waker::wake);
+            if 0 == target.plain && !target.ruled {
+                targets.remove(&self.key);
+            }
+        });
+    }
+}
+
+pub async fn remove(id: u32) -> Result<(), JsValue> {
+    update(UpdateRuleOptions {
+        add_rules: Vec::new(),
+        remove_rule_ids: vec![id],
+    })
+    .await
+}
+
+/// Removes the rules left by a service worker that stopped mid-request. They are removed once,
+/// later calls wait for that.
+pub async fn clear() -> Result<(), JsValue> {
+    CLEARED.with(Clone::clone).await
+}
+
+async fn clear_stale() -> Result<(), JsValue> {
+    let rules = JsFuture::from(get_session_rules()?).await?;
+    let rules: Vec<RuleId> = serde_wasm_bindgen::from_value(rules)?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    update(UpdateRuleOptions {
+        add_rules: Vec::new(),
+        remove_rule_ids: rules.into_iter().map(|rule| rule.id).collect(),
+    })
+    .await
+}
+
+/// What a rule for the request `method` `url` matches: the URL without its fragment, which is
+/// not part of the URL a rule sees, and the method as a rule condition names it.
+fn target(url: &str, method: &str) -> (String, String) {
+    let url = url.split('#').next().unwrap_or(url);
+    let method = method.to_ascii_lowercase();
+    let method = if RULE_METHODS.contains(&method.as_str()) {
+        method
+    } else {
+        "other".to_string()
+    };
+    (url.to_string(), method)
+}
+
+/// A regular expression matching `url` and nothing else. `urlFilter` would take `*`, `|` and
+/// `^` in it for wildcards and anchors.
+fn exact(url: &str) -> String {
+    let mut regex = String::with_capacity(url.len() + 2);
+    regex.push('^');
+    for c in url.chars() {
+        if r"\.+*?()|[]{}^$".contains(c) {
+            regex.push('\\');
+        }
+        regex.push(c);
+    }
+    regex.push('$');
+    regex
+}
+
+async fn update(options: UpdateRuleOptions) -> Result<(), JsValue> {
+    let options = options.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?;
+    JsFuture::from(update_session_rules(&options)?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_escapes_regex_syntax() {
+        assert_eq!(
+            exact("https://a.test/p?q=(a|b)*&r=[1]{2}^$+\\"),
+            r"^https://a\.test/p\?q=\(a\|b\)\*&r=\[1\]\{2\}\^\$\+\\$"
+        );
+    }
+
+    #[test]
+    fn target_drops_the_fragment_and_names_the_method() {
+        assert_eq!(
+            target("https://a.test/p?q=1#top", "PATCH"),
+            ("https://a.test/p?q=1".to_string(), "patch".to_string())
+        );
+        assert_eq!(
+            target("https://a.test/", "PROPFIND"),
+            ("https://a.test/".to_string(), "other".to_string())
+        );
+    }
+}
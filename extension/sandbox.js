@@ -0,0 +1,29 @@
+// Renders response html posted by the popup. This page runs under the manifest
+// `sandbox` CSP, the html itself goes into a nested frame whose scripts are off
+// unless the popup asks for them.
+window.addEventListener("message", (event) => {
+  if (event.source !== window.parent) {
+    return;
+  }
+
+  const { html, base, scripts } = event.data;
+  const frame = document.createElement("iframe");
+  frame.setAttribute("sandbox", scripts ? "allow-scripts" : "");
+  frame.srcdoc = withBase(html, base);
+  document.body.replaceChildren(frame);
+});
+
+function withBase(html, base) {
+  if (!base) {
+    return html;
+  }
+
+  const href = base.replace(/&/g, "&amp;").replace(/"/g, "&quot;");
+  const tag = `<base href="${href}">`;
+  const head = /<head(\s[^>]*)?>/i.exec(html);
+  if (head) {
+    const at = head.index + head[0].length;
+    return html.slice(0, at) + tag + html.slice(at);
+  }
+  return tag + html;
+}
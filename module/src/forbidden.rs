@@ -0,0 +1,91 @@
+//! Request headers and methods that fetch won't send as they were typed.
+
+/// What becomes of a forbidden request header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forbidden {
+    /// Dropped by fetch, but a `declarativeNetRequest` rule can set it on the request.
+    Rule,
+    /// Owned by the network stack, the typed value never reaches the server.
+    Never,
+}
+
+/// Headers the network stack writes itself, they frame the request on the wire.
+const NEVER: &[&str] = &[
+    "connection",
+    "content-length",
+    "cookie2",
+    "expect",
+    "host",
+    "keep-alive",
+    "set-cookie",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// The rest of the forbidden headers of the fetch standard, plus `user-agent` which Chrome
+/// still ignores.
+const RULE: &[&str] = &[
+    "accept-charset",
+    "accept-encoding",
+    "access-control-request-headers",
+    "access-control-request-method",
+    "cookie",
+    "date",
+    "dnt",
+    "origin",
+    "referer",
+    "user-agent",
+    "via",
+];
+
+/// Characters of a URL past which a `declarativeNetRequest` rule may not match it: Chrome
+/// refuses a `regexFilter` whose compiled form goes over 2KB.
+pub const RULE_URL_LIMIT: usize = 1000;
+
+/// Methods fetch throws on.
+const METHODS: &[&str] = &["CONNECT", "TRACE", "TRACK"];
+
+/// Whether fetch drops the header `name`, and if it can still be sent.
+pub fn header(name: &str) -> Option<Forbidden> {
+    let name = name.trim().to_ascii_lowercase();
+    if name.starts_with("proxy-") || NEVER.contains(&name.as_str()) {
+        Some(Forbidden::Never)
+    } else if name.starts_with("sec-") || RULE.contains(&name.as_str()) {
+        Some(Forbidden::Rule)
+    } else {
+        None
+    }
+}
+
+/// Whether fetch refuses to send `method`.
+pub fn method(method: &str) -> bool {
+    METHODS.contains(&method.trim().to_ascii_uppercase().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers() {
+        assert_eq!(header("Cookie"), Some(Forbidden::Rule));
+        assert_eq!(header("sec-ch-ua"), Some(Forbidden::Rule));
+        assert_eq!(header(" User-Agent "), Some(Forbidden::Rule));
+        assert_eq!(header("Host"), Some(Forbidden::Never));
+        assert_eq!(header("Proxy-Authorization"), Some(Forbidden::Never));
+        assert_eq!(header("Content-Length"), Some(Forbidden::Never));
+        assert_eq!(header("Authorization"), None);
+        assert_eq!(header("x-cookie"), None);
+    }
+
+    #[test]
+    fn methods() {
+        assert!(method("connect"));
+        assert!(method("TRACE"));
+        assert!(method(" track "));
+        assert!(!method("GET"));
+        assert!(!method("PROPFIND"));
+    }
+}
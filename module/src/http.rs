@@ -10,6 +10,7 @@ pub struct Request {
     pub method: String,
     pub uri: String,
     pub header: HashMap<String, String>,
+    #[serde(with = "crate::bytes")]
     pub body: Vec<u8>,
 }
 
@@ -31,14 +32,43 @@ pub struct Response {
     pub done_date: OffsetDateTime,
     pub status: u16,
     pub header: HashMap<String, Vec<u8>>,
+    #[serde(with = "crate::bytes")]
     pub body: Vec<u8>,
     pub elapsed_time: i32,
+    /// Final URI after redirects.
+    #[serde(default)]
+    pub uri: String,
+}
+
+/// A request sent over the [`PORT_NAME`] port. `id` lets a reconnecting popup attach to
+/// the transfer again with an `http_attach` message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transfer {
+    pub id: String,
+    pub request: Request,
 }
 
-impl TryFrom<Request> for Message {
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Waiting for the response head.
+    Sending,
+    /// Reading the response body.
+    Receiving,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub phase: Phase,
+    pub received: u64,
+    /// `content-length` of the response, if any.
+    pub total: Option<u64>,
+}
+
+impl TryFrom<Transfer> for Message {
     type Error = serde_json::Error;
 
-    fn try_from(value: Request) -> Result<Self, Self::Error> {
+    fn try_from(value: Transfer) -> Result<Self, Self::Error> {
         Ok(Self {
             code: "http_send".to_string(),
             value: serde_json::to_string(&value)?,
@@ -46,6 +76,15 @@ impl TryFrom<Request> for Message {
     }
 }
 
+impl From<Progress> for Message {
+    fn from(value: Progress) -> Self {
+        Self {
+            code: "progress".to_string(),
+            value: serde_json::to_string(&value).unwrap_or_default(),
+        }
+    }
+}
+
 impl From<Response> for Message {
     fn from(value: Response) -> Self {
         let value = serde_json::to_string(&value);
@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+pub mod bytes;
+pub mod forbidden;
 pub mod http;
 
+/// Name of the `runtime.connect` port that carries http transfers.
+pub const PORT_NAME: &str = "http";
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub code: String,
@@ -1,20 +1,710 @@
-use leptos::{html::Div, prelude::*};
-use thaw_utils::class_list;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use uuid::Uuid;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{js_sys::Uint8Array, DragEvent, File, HtmlInputElement};
+
+use crate::editor::CodeEditor;
+use crate::params::encode;
+use crate::preview::format_size;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    #[default]
+    Raw,
+    Form,
+    Multipart,
+    Binary,
+}
+
+/// What a raw body is written in, it decides the `Content-Type`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    #[default]
+    Text,
+    Json,
+    Xml,
+    Html,
+    JavaScript,
+    Yaml,
+    GraphQl,
+}
+
+impl Language {
+    const ALL: [Self; 7] = [
+        Self::Text,
+        Self::Json,
+        Self::Xml,
+        Self::Html,
+        Self::JavaScript,
+        Self::Yaml,
+        Self::GraphQl,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Text => "Text",
+            Self::Json => "JSON",
+            Self::Xml => "XML",
+            Self::Html => "HTML",
+            Self::JavaScript => "JavaScript",
+            Self::Yaml => "YAML",
+            Self::GraphQl => "GraphQL",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Text => "text/plain",
+            Self::Json => "application/json",
+            Self::Xml => "application/xml",
+            Self::Html => "text/html",
+            Self::JavaScript => "application/javascript",
+            Self::Yaml => "application/yaml",
+            Self::GraphQl => "application/graphql",
+        }
+    }
+}
+
+/// A `application/x-www-form-urlencoded` field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Field {
+    pub enabled: bool,
+    pub name: String,
+    pub value: String,
+}
+
+/// A file picked or dropped into the editor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileData {
+    pub name: String,
+    pub mime: String,
+    #[serde(with = "module::bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PartValue {
+    Text(String),
+    File(Option<FileData>),
+}
+
+/// A `multipart/form-data` part.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Part {
+    pub enabled: bool,
+    pub name: String,
+    pub value: PartValue,
+}
+
+/// Body of a request in every mode, switching modes keeps what the others hold. The raw text
+/// is kept apart, it changes with every key typed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BodyState {
+    pub mode: Mode,
+    pub language: Language,
+    pub fields: Vec<Field>,
+    pub parts: Vec<Part>,
+    pub file: Option<FileData>,
+}
+
+impl BodyState {
+    /// The body bytes and the `Content-Type` they go with, `raw` being the editor text.
+    pub fn encode(&self, raw: String) -> (Vec<u8>, Option<String>) {
+        match self.mode {
+            Mode::Raw if raw.is_empty() => (Vec::new(), None),
+            Mode::Raw => (
+                raw.into_bytes(),
+                Some(self.language.content_type().to_string()),
+            ),
+            Mode::Form => {
+                let body = self
+                    .fields
+                    .iter()
+                    .filter(|field| field.enabled)
+                    .map(|field| {
+                        format!("{}={}", form_encode(&field.name), form_encode(&field.value))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("&");
+                (
+                    body.into_bytes(),
+                    Some("application/x-www-form-urlencoded".to_string()),
+                )
+            }
+            Mode::Multipart => {
+                let boundary = format!("----CDGHOPT{}", Uuid::now_v7().simple());
+                let mut body = Vec::new();
+                for part in self.parts.iter().filter(|part| part.enabled) {
+                    body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+                    let name = quote(&part.name);
+                    match &part.value {
+                        PartValue::Text(text) => {
+                            body.extend_from_slice(
+                                format!("Content-Disposition: form-data; name=\"{name}\"\r\n\r\n")
+                                    .as_bytes(),
+                            );
+                            body.extend_from_slice(text.as_bytes());
+                        }
+                        PartValue::File(file) => {
+                            let (file_name, mime, data) = file
+                                .as_ref()
+                                .map_or(("", "application/octet-stream", &[][..]), |file| {
+                                    (file.name.as_str(), file.mime.as_str(), &file.data[..])
+                                });
+                            body.extend_from_slice(
+                                format!(
+                                    "Content-Disposition: form-data; name=\"{name}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                                    quote(file_name),
+                                    mime_or_default(mime),
+                                )
+                                .as_bytes(),
+                            );
+                            body.extend_from_slice(data);
+                        }
+                    }
+                    body.extend_from_slice(b"\r\n");
+                }
+                body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+                (
+                    body,
+                    Some(format!("multipart/form-data; boundary={boundary}")),
+                )
+            }
+            Mode::Binary => match &self.file {
+                Some(file) => (
+                    file.data.clone(),
+                    Some(mime_or_default(&file.mime).to_string()),
+                ),
+                None => (Vec::new(), None),
+            },
+        }
+    }
+
+    /// Forgets the files picked, they are picked again rather than kept in storage.
+    pub fn drop_files(&mut self) {
+        for part in self.parts.iter_mut() {
+            if let PartValue::File(file) = &mut part.value {
+                *file = None;
+            }
+        }
+        self.file = None;
+    }
+
+    /// Bytes of the files held, for the storage estimate.
+    pub fn file_bytes(&self) -> usize {
+        let parts: usize = self
+            .parts
+            .iter()
+            .filter_map(|part| match &part.value {
+                PartValue::File(Some(file)) => Some(file.data.len()),
+                _ => None,
+            })
+            .sum();
+        parts + self.file.as_ref().map_or(0, |file| file.data.len())
+    }
+}
+
+/// `header` with the `Content-Type` of the body. A multipart body must carry its own boundary,
+/// otherwise a type set by hand wins.
+pub fn with_content_type(
+    mut header: Vec<(String, String)>,
+    mode: Mode,
+    content_type: Option<String>,
+) -> Vec<(String, String)> {
+    let Some(content_type) = content_type else {
+        return header;
+    };
+    let existing = header
+        .iter()
+        .position(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+    match existing {
+        Some(at) if Mode::Multipart == mode => header[at].1 = content_type,
+        Some(_) => {}
+        None => header.push(("content-type".to_string(), content_type)),
+    }
+    header
+}
+
+/// Mode tabs and the editor of the mode chosen, `raw` being the text of the raw mode.
+#[component]
+pub fn BodyEditor(state: RwSignal<BodyState>, raw: RwSignal<String>) -> impl IntoView {
+    let mode = move || state.with(|state| state.mode);
+    let tab = move |label: &'static str, tab_mode: Mode| {
+        view! {
+            <a
+                role="tab"
+                class="tab"
+                class:tab-active=move || tab_mode == mode()
+                on:click=move |_| state.update(|state| state.mode = tab_mode)
+            >
+                {label}
+            </a>
+        }
+    };
+
+    view! {
+        <div class="flex items-center gap-2">
+            <div role="tablist" class="tabs tabs-boxed tabs-xs">
+                {tab("Raw", Mode::Raw)}
+                {tab("Form", Mode::Form)}
+                {tab("Multipart", Mode::Multipart)}
+                {tab("Binary", Mode::Binary)}
+            </div>
+            <Show when=move || Mode::Raw == mode()>
+                <select
+                    class="select select-xs"
+                    on:change=move |ev| {
+                        let index = event_target_value(&ev).parse::<usize>().unwrap_or_default();
+                        state.update(|state| state.language = Language::ALL[index]);
+                    }
+                >
+                    {Language::ALL
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, language)| {
+                            view! {
+                                <option
+                                    value=index.to_string()
+                                    selected=move || state.with(|state| language == state.language)
+                                >
+                                    {language.name()}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+            </Show>
+        </div>
+        <div class:hidden=move || Mode::Raw != mode() class="h-full w-full">
+            <CodeEditor
+                value=raw
+                language=Signal::derive(move || state.with(|state| state.language))
+            />
+        </div>
+        <Show when=move || Mode::Form == mode()>
+            <FormTable state=state />
+        </Show>
+        <Show when=move || Mode::Multipart == mode()>
+            <MultipartTable state=state />
+        </Show>
+        <Show when=move || Mode::Binary == mode()>
+            <FileDrop on_file=move |file| state.update(|state| state.file = Some(file))>
+                {move || {
+                    state
+                        .with(|state| {
+                            state
+                                .file
+                                .as_ref()
+                                .map(|file| {
+                                    format!("{} ({})", file.name, format_size(file.data.len()))
+                                })
+                        })
+                        .unwrap_or_else(|| "Drop a file here or pick one".to_string())
+                }}
+            </FileDrop>
+        </Show>
+    }
+}
+
+#[component]
+fn FormTable(state: RwSignal<BodyState>) -> impl IntoView {
+    let edit =
+        move |change: &dyn Fn(&mut Vec<Field>)| state.update(|state| change(&mut state.fields));
+    view! {
+        <table class="table table-xs w-full">
+            <tbody>
+                <For
+                    each=move || state.with(|state| state.fields.clone()).into_iter().enumerate()
+                    key=|(index, field)| (*index, field.clone())
+                    children=move |(index, field)| {
+                        view! {
+                            <tr>
+                                <td class="w-6">
+                                    <input
+                                        type="checkbox"
+                                        class="checkbox checkbox-xs"
+                                        prop:checked=field.enabled
+                                        on:change=move |ev| {
+                                            let enabled = event_target_checked(&ev);
+                                            edit(&|fields| fields[index].enabled = enabled);
+                                        }
+                                    />
+                                </td>
+                                <td class="w-1/3">
+                                    <input
+                                        type="text"
+                                        class="input input-sm rounded-none w-full"
+                                        prop:value=field.name
+                                        on:change=move |ev| {
+                                            let name = event_target_value(&ev);
+                                            edit(&|fields| fields[index].name = name.clone());
+                                        }
+                                    />
+                                </td>
+                                <td>
+                                    <input
+                                        type="text"
+                                        class="input input-sm rounded-none w-full"
+                                        prop:value=field.value
+                                        on:change=move |ev| {
+                                            let value = event_target_value(&ev);
+                                            edit(&|fields| fields[index].value = value.clone());
+                                        }
+                                    />
+                                </td>
+                                <td class="w-6">
+                                    <button
+                                        class="btn btn-ghost btn-xs"
+                                        title="Remove"
+                                        on:click=move |_| {
+                                            edit(&|fields| {
+                                                fields.remove(index);
+                                            })
+                                        }
+                                    >
+                                        "✕"
+                                    </button>
+                                </td>
+                            </tr>
+                        }
+                    }
+                />
+                <tr>
+                    <td></td>
+                    <td colspan="3">
+                        <input
+                            type="text"
+                            class="input input-sm rounded-none w-full"
+                            placeholder="Add field..."
+                            on:change=move |ev| {
+                                let name = event_target_value(&ev);
+                                if !name.is_empty() {
+                                    edit(&|fields| {
+                                        fields
+                                            .push(Field {
+                                                enabled: true,
+                                                name: name.clone(),
+                                                value: String::new(),
+                                            })
+                                    });
+                                }
+                                event_target::<HtmlInputElement>(&ev).set_value("");
+                            }
+                        />
+                    </td>
+                </tr>
+            </tbody>
+        </table>
+    }
+}
 
 #[component]
-pub fn BodyArea(
-    node_ref: NodeRef<Div>,
-    class: &'static str,
-    #[prop(into)] value: Signal<String>,
-    #[prop(into)] contenteditable: Signal<bool>,
+fn MultipartTable(state: RwSignal<BodyState>) -> impl IntoView {
+    let edit =
+        move |change: &dyn Fn(&mut Vec<Part>)| state.update(|state| change(&mut state.parts));
+    let add = move |value: PartValue| {
+        state.update(|state| {
+            state.parts.push(Part {
+                enabled: true,
+                name: String::new(),
+                value,
+            })
+        })
+    };
+    view! {
+        <table class="table table-xs w-full">
+            <tbody>
+                <For
+                    each=move || state.with(|state| state.parts.clone()).into_iter().enumerate()
+                    key=|(index, part)| (*index, part.clone())
+                    children=move |(index, part)| {
+                        let value = match part.value {
+                            PartValue::Text(text) => {
+                                view! {
+                                    <input
+                                        type="text"
+                                        class="input input-sm rounded-none w-full"
+                                        prop:value=text
+                                        on:change=move |ev| {
+                                            let text = event_target_value(&ev);
+                                            edit(&|parts| parts[index].value = PartValue::Text(text.clone()));
+                                        }
+                                    />
+                                }
+                                    .into_any()
+                            }
+                            PartValue::File(file) => {
+                                let label = file
+                                    .map(|file| format!("{} ({})", file.name, format_size(file.data.len())))
+                                    .unwrap_or_else(|| "Drop a file here or pick one".to_string());
+                                view! {
+                                    <FileDrop on_file=move |file| {
+                                        edit(&|parts| parts[index].value = PartValue::File(Some(file.clone())))
+                                    }>{label}</FileDrop>
+                                }
+                                    .into_any()
+                            }
+                        };
+                        view! {
+                            <tr>
+                                <td class="w-6">
+                                    <input
+                                        type="checkbox"
+                                        class="checkbox checkbox-xs"
+                                        prop:checked=part.enabled
+                                        on:change=move |ev| {
+                                            let enabled = event_target_checked(&ev);
+                                            edit(&|parts| parts[index].enabled = enabled);
+                                        }
+                                    />
+                                </td>
+                                <td class="w-1/3">
+                                    <input
+                                        type="text"
+                                        class="input input-sm rounded-none w-full"
+                                        placeholder="Name..."
+                                        prop:value=part.name
+                                        on:change=move |ev| {
+                                            let name = event_target_value(&ev);
+                                            edit(&|parts| parts[index].name = name.clone());
+                                        }
+                                    />
+                                </td>
+                                <td>{value}</td>
+                                <td class="w-6">
+                                    <button
+                                        class="btn btn-ghost btn-xs"
+                                        title="Remove"
+                                        on:click=move |_| {
+                                            edit(&|parts| {
+                                                parts.remove(index);
+                                            })
+                                        }
+                                    >
+                                        "✕"
+                                    </button>
+                                </td>
+                            </tr>
+                        }
+                    }
+                />
+                <tr>
+                    <td></td>
+                    <td colspan="3" class="flex gap-1">
+                        <button
+                            class="btn btn-xs"
+                            on:click=move |_| add(PartValue::Text(String::new()))
+                        >
+                            "+ Text"
+                        </button>
+                        <button class="btn btn-xs" on:click=move |_| add(PartValue::File(None))>
+                            "+ File"
+                        </button>
+                    </td>
+                </tr>
+            </tbody>
+        </table>
+    }
+}
+
+/// A file picker that also takes a file dropped on it.
+#[component]
+fn FileDrop(
+    on_file: impl Fn(FileData) + Copy + Send + Sync + 'static,
+    children: Children,
 ) -> impl IntoView {
+    let over = RwSignal::new(false);
+    let take = move |file: Option<File>| {
+        if let Some(file) = file {
+            leptos::task::spawn_local(async move {
+                match read(file).await {
+                    Ok(file) => on_file(file),
+                    Err(e) => error!("Failed to read file: {e:?}"),
+                }
+            });
+        }
+    };
+
     view! {
-        <div
-            class=class_list!["textarea", "rounded-none", class]
-            node_ref=node_ref
-            contenteditable=move || contenteditable.get().to_string()
+        <label
+            class="flex w-full cursor-pointer items-center justify-center rounded border border-dashed p-2 text-sm"
+            class:border-primary=over
+            on:dragover=move |ev: DragEvent| {
+                ev.prevent_default();
+                over.set(true);
+            }
+            on:dragleave=move |_| over.set(false)
+            on:drop=move |ev: DragEvent| {
+                ev.prevent_default();
+                over.set(false);
+                take(ev.data_transfer().and_then(|data| data.files()).and_then(|files| files.get(0)));
+            }
         >
-            {value}
-        </div>
+            {children()}
+            <input
+                type="file"
+                class="hidden"
+                on:change=move |ev| {
+                    let input = event_target::<HtmlInputElement>(&ev);
+                    take(input.files().and_then(|files| files.get(0)));
+                    input.set_value("");
+                }
+            />
+        </label>
+    }
+}
+
+async fn read(file: File) -> Result<FileData, JsValue> {
+    let buffer = JsFuture::from(file.array_buffer()).await?;
+    Ok(FileData {
+        name: file.name(),
+        mime: file.type_(),
+        data: buffer
+            .dyn_into()
+            .map(|buffer| Uint8Array::new(&buffer).to_vec())?,
+    })
+}
+
+fn form_encode(component: &str) -> String {
+    encode(component).replace("%20", "+")
+}
+
+/// `value` for a quoted multipart header parameter.
+fn quote(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn mime_or_default(mime: &str) -> &str {
+    if mime.is_empty() {
+        "application/octet-stream"
+    } else {
+        mime
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, mime: &str, data: &[u8]) -> FileData {
+        FileData {
+            name: name.to_string(),
+            mime: mime.to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn raw_takes_the_language_type() {
+        let mut state = BodyState::default();
+        assert_eq!(state.encode(String::new()), (Vec::new(), None));
+        state.language = Language::Json;
+        assert_eq!(
+            state.encode("{}".to_string()),
+            (b"{}".to_vec(), Some("application/json".to_string()))
+        );
+    }
+
+    #[test]
+    fn form_encodes_enabled_fields() {
+        let field = |enabled, name: &str, value: &str| Field {
+            enabled,
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        let state = BodyState {
+            mode: Mode::Form,
+            fields: vec![
+                field(true, "q", "a b&c"),
+                field(false, "off", "1"),
+                field(true, "é", "+"),
+            ],
+            ..BodyState::default()
+        };
+        let (body, content_type) = state.encode(String::new());
+        assert_eq!(body, b"q=a+b%26c&%C3%A9=%2B");
+        assert_eq!(
+            content_type.as_deref(),
+            Some("application/x-www-form-urlencoded")
+        );
+    }
+
+    #[test]
+    fn multipart_frames_enabled_parts() {
+        let part = |enabled, name: &str, value| Part {
+            enabled,
+            name: name.to_string(),
+            value,
+        };
+        let state = BodyState {
+            mode: Mode::Multipart,
+            parts: vec![
+                part(true, "text", PartValue::Text("v".to_string())),
+                part(false, "off", PartValue::Text("x".to_string())),
+                part(
+                    true,
+                    "up\"load",
+                    PartValue::File(Some(file("a.bin", "", &[0, 1]))),
+                ),
+            ],
+            ..BodyState::default()
+        };
+        let (body, content_type) = state.encode(String::new());
+        let content_type = content_type.unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let mut expected = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"text\"\r\n\r\nv\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"up%22load\"; filename=\"a.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n"
+        )
+        .into_bytes();
+        expected.extend_from_slice(&[0, 1]);
+        expected.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn binary_sends_the_file() {
+        let mut state = BodyState {
+            mode: Mode::Binary,
+            ..BodyState::default()
+        };
+        assert_eq!(state.encode(String::new()), (Vec::new(), None));
+        state.file = Some(file("a.png", "image/png", &[1, 2, 3]));
+        assert_eq!(
+            state.encode("ignored".to_string()),
+            (vec![1, 2, 3], Some("image/png".to_string()))
+        );
+    }
+
+    #[test]
+    fn content_type_set_by_hand_wins_but_multipart() {
+        let typed = vec![("Content-Type".to_string(), "text/csv".to_string())];
+        let json = Some("application/json".to_string());
+        assert_eq!(
+            with_content_type(typed.clone(), Mode::Raw, json.clone()),
+            typed
+        );
+        assert_eq!(with_content_type(typed.clone(), Mode::Raw, None), typed);
+        assert_eq!(
+            with_content_type(Vec::new(), Mode::Raw, json),
+            vec![("content-type".to_string(), "application/json".to_string())]
+        );
+        let multipart = "multipart/form-data; boundary=b".to_string();
+        assert_eq!(
+            with_content_type(typed, Mode::Multipart, Some(multipart.clone())),
+            vec![("Content-Type".to_string(), multipart)]
+        );
     }
 }
@@ -1,10 +1,20 @@
-use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+use leptos::prelude::set_timeout;
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
 use serde_wasm_bindgen::from_value;
 #[allow(deprecated)]
 use wasm_bindgen::JsStatic;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
 use web_extensions_sys::Browser;
+use web_sys::{
+    js_sys::{Map, Object},
+    HtmlAnchorElement, Url,
+};
+
+use crate::db::to_js;
+use crate::preview::object_url;
 
 #[allow(deprecated)]
 pub fn browser() -> &'static JsStatic<Browser> {
@@ -33,3 +43,39 @@ pub async fn get_local<T: DeserializeOwned>(key: &str) -> Result<T, JsValue> {
     }
     Err(JsValue::NULL)
 }
+
+pub async fn set_local<T: Serialize>(key: &str, value: &T) -> Result<(), JsValue> {
+    let items = Map::new().set(&JsValue::from_str(key), &to_js(value)?);
+    browser()
+        .storage()
+        .local()
+        .set(&Object::from_entries(&items)?)
+        .await?;
+    Ok(())
+}
+
+pub fn copy_text(text: &str) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.navigator().clipboard().write_text(text);
+    }
+}
+
+/// Saves `body` as a file named `file_name` through the browser's downloads.
+pub fn download(body: &[u8], mime: &str, file_name: &str) -> Result<(), JsValue> {
+    let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+        return Ok(());
+    };
+    let url = object_url(body, mime)?;
+    let anchor: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    // The download reads the blob after this returns.
+    set_timeout(
+        move || {
+            let _ = Url::revoke_object_url(&url);
+        },
+        Duration::from_secs(10),
+    );
+    Ok(())
+}
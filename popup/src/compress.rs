@@ -0,0 +1,298 @@
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec};
+use module::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+use crate::body::{Mode, PartValue};
+use crate::log::LogContent;
+
+/// Bodies up to this many bytes are stored as they are, deflating them saves next to nothing.
+const COMPRESS_ABOVE: usize = 1024;
+const LEVEL: u8 = 6;
+
+/// How the bodies of a [`LogContent`] are kept in storage.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bodies {
+    pub request: Stored,
+    pub response: Stored,
+    /// The files of the body state were left out, those sent are read back from the request
+    /// body.
+    pub files_in_body: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stored {
+    /// The body is raw deflate.
+    pub deflated: bool,
+    /// Length before truncation, 0 for entries stored before it was recorded.
+    pub size: usize,
+    /// The body was cut at the cap of [`Retention::max_body_bytes`](crate::retention::Retention::max_body_bytes).
+    pub truncated: bool,
+}
+
+impl Bodies {
+    pub fn truncated(&self) -> bool {
+        self.request.truncated || self.response.truncated
+    }
+}
+
+/// Records the body sizes of `content` and cuts bodies longer than `cap`.
+pub fn truncate(content: &mut LogContent, cap: Option<usize>) {
+    let bodies = &mut content.bodies;
+    truncate_body(&mut content.request.body, &mut bodies.request, cap);
+    if let Some(response) = &mut content.response {
+        truncate_body(&mut response.body, &mut bodies.response, cap);
+    }
+}
+
+/// Deflates the bodies of `content` above [`COMPRESS_ABOVE`], where it makes them smaller, and
+/// leaves out the file bytes of its body state.
+pub fn pack(content: &mut LogContent) {
+    leave_out_files(content);
+    let bodies = &mut content.bodies;
+    deflate(&mut content.request.body, &mut bodies.request);
+    if let Some(response) = &mut content.response {
+        deflate(&mut response.body, &mut bodies.response);
+    }
+}
+
+/// Reverses [`pack`].
+pub fn unpack(content: &mut LogContent) -> Result<(), JsValue> {
+    let bodies = &mut content.bodies;
+    inflate(&mut content.request.body, &mut bodies.request)?;
+    if let Some(response) = &mut content.response {
+        inflate(&mut response.body, &mut bodies.response)?;
+    }
+    read_back_files(content);
+    Ok(())
+}
+
+/// Keeps a single copy of the files: those sent are in the request body already, the others
+/// never reached the server.
+fn leave_out_files(content: &mut LogContent) {
+    let state = &mut content.body_state;
+    let mode = state.mode;
+    match (&mut state.file, mode) {
+        (Some(file), Mode::Binary) => file.data.clear(),
+        (file, _) => *file = None,
+    }
+    for part in state.parts.iter_mut() {
+        if let PartValue::File(Some(file)) = &mut part.value {
+            if Mode::Multipart == mode && part.enabled {
+                file.data.clear();
+            } else {
+                part.value = PartValue::File(None);
+            }
+        }
+    }
+    content.bodies.files_in_body = true;
+}
+
+/// Reverses [`leave_out_files`] for the files sent, from the request body as stored.
+fn read_back_files(content: &mut LogContent) {
+    if !content.bodies.files_in_body {
+        return;
+    }
+    content.bodies.files_in_body = false;
+    let request = &content.request;
+    let state = &mut content.body_state;
+    match state.mode {
+        Mode::Binary => {
+            if let Some(file) = &mut state.file {
+                file.data = request.body.clone();
+            }
+        }
+        Mode::Multipart => {
+            let Some(boundary) = boundary(request) else {
+                return;
+            };
+            // The preamble before the first delimiter is empty.
+            let mut sections = split(&request.body, format!("--{boundary}").as_bytes())
+                .into_iter()
+                .skip(1);
+            for part in state.parts.iter_mut().filter(|part| part.enabled) {
+                let Some(section) = sections.next() else {
+                    break;
+                };
+                if let PartValue::File(Some(file)) = &mut part.value {
+                    let data = find(section, b"\r\n\r\n").map_or(&[][..], |at| &section[at + 4..]);
+                    file.data = data.strip_suffix(b"\r\n").unwrap_or(data).to_vec();
+                }
+            }
+        }
+        Mode::Raw | Mode::Form => {}
+    }
+}
+
+/// Boundary of a `multipart/form-data` request.
+fn boundary(request: &Request) -> Option<String> {
+    let (_, content_type) = request
+        .header
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))?;
+    content_type.split(';').find_map(|param| {
+        let boundary = param.trim().strip_prefix("boundary=")?;
+        Some(boundary.trim_matches('"').to_string())
+    })
+}
+
+/// The pieces of `haystack` between occurrences of `delimiter`.
+fn split<'a>(haystack: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut pieces = Vec::new();
+    let mut rest = haystack;
+    while let Some(at) = find(rest, delimiter) {
+        pieces.push(&rest[..at]);
+        rest = &rest[at + delimiter.len()..];
+    }
+    pieces.push(rest);
+    pieces
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| needle == window)
+}
+
+fn truncate_body(body: &mut Vec<u8>, stored: &mut Stored, cap: Option<usize>) {
+    stored.size = body.len();
+    let Some(cap) = cap.filter(|cap| body.len() > *cap) else {
+        return;
+    };
+    // Don't split the last character of a text body.
+    let end = match std::str::from_utf8(&body[..cap]) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => cap,
+    };
+    body.truncate(end);
+    stored.truncated = true;
+}
+
+fn deflate(body: &mut Vec<u8>, stored: &mut Stored) {
+    if stored.deflated || body.len() <= COMPRESS_ABOVE {
+        return;
+    }
+    let deflated = compress_to_vec(body, LEVEL);
+    if deflated.len() < body.len() {
+        *body = deflated;
+        stored.deflated = true;
+    }
+}
+
+fn inflate(body: &mut Vec<u8>, stored: &mut Stored) -> Result<(), JsValue> {
+    if !stored.deflated {
+        return Ok(());
+    }
+    *body = decompress_to_vec(body)
+        .map_err(|e| JsValue::from_str(&format!("Failed to inflate body: {e}")))?;
+    stored.deflated = false;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::{BodyState, FileData, Part};
+
+    fn file(data: &[u8]) -> Option<FileData> {
+        Some(FileData {
+            name: "a.bin".to_string(),
+            mime: String::new(),
+            data: data.to_vec(),
+        })
+    }
+
+    fn logged(body_state: BodyState) -> LogContent {
+        let (body, content_type) = body_state.encode(String::new());
+        let header = content_type
+            .map(|content_type| ("Content-Type".to_string(), content_type))
+            .into_iter()
+            .collect();
+        LogContent {
+            request: Request::new(
+                "POST".to_string(),
+                "https://a.test".to_string(),
+                header,
+                body,
+            ),
+            response: None,
+            error: None,
+            filter: String::new(),
+            bodies: Bodies::default(),
+            body_state,
+        }
+    }
+
+    #[test]
+    fn truncate_body_keeps_whole_characters() {
+        let mut stored = Stored::default();
+        let mut body = "héllo".as_bytes().to_vec();
+        truncate_body(&mut body, &mut stored, Some(2));
+        assert_eq!(body, b"h");
+        assert_eq!((stored.size, stored.truncated), (6, true));
+
+        let mut stored = Stored::default();
+        let mut body = vec![0xff, 0xfe, 0xfd];
+        truncate_body(&mut body, &mut stored, Some(2));
+        assert_eq!(body, [0xff, 0xfe]);
+
+        let mut stored = Stored::default();
+        let mut body = b"short".to_vec();
+        truncate_body(&mut body, &mut stored, Some(5));
+        assert_eq!(body, b"short");
+        assert_eq!((stored.size, stored.truncated), (5, false));
+    }
+
+    #[test]
+    fn pack_round_trips_bodies() {
+        let mut content = logged(BodyState::default());
+        content.request.body = "a".repeat(4 * COMPRESS_ABOVE).into_bytes();
+        let original = content.request.body.clone();
+        pack(&mut content);
+        assert!(content.bodies.request.deflated);
+        assert!(content.request.body.len() < original.len());
+        unpack(&mut content).unwrap();
+        assert_eq!(content.request.body, original);
+    }
+
+    #[test]
+    fn files_are_kept_once() {
+        let part = |enabled, value| Part {
+            enabled,
+            name: "f".to_string(),
+            value,
+        };
+        let state = BodyState {
+            mode: Mode::Multipart,
+            parts: vec![
+                part(true, PartValue::File(file(b"one\r\n--x"))),
+                part(true, PartValue::Text("text".to_string())),
+                part(false, PartValue::File(file(b"unsent"))),
+                part(true, PartValue::File(file(b""))),
+            ],
+            file: file(b"other mode"),
+            ..BodyState::default()
+        };
+        let mut content = logged(state.clone());
+        pack(&mut content);
+        assert_eq!(content.body_state.file_bytes(), 0);
+        unpack(&mut content).unwrap();
+
+        let mut expected = state;
+        expected.parts[2].value = PartValue::File(None);
+        expected.file = None;
+        assert_eq!(content.body_state, expected);
+
+        let state = BodyState {
+            mode: Mode::Binary,
+            file: file(&[0, 1, 2]),
+            ..BodyState::default()
+        };
+        let mut content = logged(state.clone());
+        pack(&mut content);
+        unpack(&mut content).unwrap();
+        assert_eq!(content.body_state, state);
+    }
+}
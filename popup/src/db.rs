@@ -0,0 +1,49 @@
+use indexed_db_futures::prelude::*;
+use serde::{de::DeserializeOwned, ser::Serializer as _, Serialize};
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::JsValue;
+
+const NAME: &str = "cdghopt";
+const VERSION: u32 = 1;
+
+/// History index records, keyed by the log id.
+pub const INDEXES: &str = "indexes";
+/// History request/response contents, keyed by the log id.
+pub const CONTENTS: &str = "contents";
+/// Fields of [`INDEXES`] records that get an IndexedDB index.
+pub const INDEXED_FIELDS: &[&str] = &["done_date", "host", "method", "status", "starred"];
+
+pub async fn open() -> Result<IdbDatabase, JsValue> {
+    let mut request = IdbDatabase::open_u32(NAME, VERSION)?;
+    request.set_on_upgrade_needed(Some(
+        |event: &IdbVersionChangeEvent| -> Result<(), JsValue> {
+            let db = event.db();
+            if !db.object_store_names().any(|name| INDEXES == name) {
+                let store = db.create_object_store(INDEXES)?;
+                for field in INDEXED_FIELDS {
+                    store.create_index(field, &IdbKeyPath::str(field))?;
+                }
+            }
+            if !db.object_store_names().any(|name| CONTENTS == name) {
+                db.create_object_store(CONTENTS)?;
+            }
+            Ok(())
+        },
+    ));
+    Ok(request.await?)
+}
+
+pub fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    Ok(Serializer::json_compatible().serialize_some(value)?)
+}
+
+pub fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsValue> {
+    Ok(serde_wasm_bindgen::from_value(value)?)
+}
+
+pub async fn get<T: DeserializeOwned>(store: &str, key: &str) -> Result<Option<T>, JsValue> {
+    let db = open().await?;
+    let tx = db.transaction_on_one(store)?;
+    let value = tx.object_store(store)?.get_owned(key)?.await?;
+    value.map(from_js).transpose()
+}
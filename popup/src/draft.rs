@@ -0,0 +1,219 @@
+use std::{collections::HashSet, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_wasm_bindgen::from_value;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use wasm_bindgen::JsValue;
+use web_extensions_sys::Tab;
+use web_sys::js_sys::{Array, Object, Reflect};
+
+use crate::body::BodyState;
+use crate::browser::{browser, get_local, set_local};
+use crate::params::{Param, PathVar};
+use crate::redact::redaction;
+use crate::vault::{sealing_key, unlocked_key, Key};
+
+/// Prefix of the `storage.local` keys of drafts, followed by the browser tab id.
+const DRAFT_PREFIX: &str = "draft_";
+/// Tab id of a page not shown in a tab.
+const TAB_ID_NONE: i32 = -1;
+/// Quiet time after the last edit before the draft is saved.
+pub const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The request tabs open in a CDGHOPT browser tab.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Draft {
+    pub tabs: Vec<TabDraft>,
+    pub active: Uuid,
+    #[serde(with = "time::serde::timestamp")]
+    pub saved: OffsetDateTime,
+}
+
+/// The request being edited in one request tab.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TabDraft {
+    pub id: Uuid,
+    pub method: String,
+    pub uri: String,
+    pub header: Vec<(String, String)>,
+    pub body: String,
+    #[serde(default)]
+    pub filter: String,
+    /// History entry whose response the tab shows.
+    #[serde(default)]
+    pub log_id: Option<Uuid>,
+    /// Query parameters, including disabled ones not in `uri`.
+    #[serde(default)]
+    pub params: Vec<Param>,
+    #[serde(default)]
+    pub path_vars: Vec<PathVar>,
+    #[serde(default)]
+    pub body_state: BodyState,
+    /// Id of a send still running in the background, the request is rebuilt from the editor.
+    #[serde(default)]
+    pub transfer: Option<String>,
+}
+
+/// What [`restore`] found for this browser tab.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Restored {
+    Nothing,
+    Draft(Draft),
+    /// A draft sealed while the history is locked. It is left as stored until unlocked.
+    Locked,
+}
+
+/// A [`Draft`] as stored, sealed with the vault key while the history is encrypted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Stored {
+    Sealed {
+        sealed: String,
+        #[serde(with = "time::serde::timestamp")]
+        saved: OffsetDateTime,
+    },
+    Plain(Draft),
+}
+
+impl Stored {
+    fn saved(&self) -> OffsetDateTime {
+        match self {
+            Self::Sealed { saved, .. } => *saved,
+            Self::Plain(draft) => draft.saved,
+        }
+    }
+
+    fn open(self, key: Option<&Key>) -> Result<Restored, JsValue> {
+        match (self, key) {
+            (Self::Plain(draft), _) => Ok(Restored::Draft(draft)),
+            (Self::Sealed { sealed, .. }, Some(key)) => {
+                key.open_value(&sealed).map(Restored::Draft)
+            }
+            (Self::Sealed { .. }, None) => Ok(Restored::Locked),
+        }
+    }
+}
+
+impl Draft {
+    pub fn is_empty(&self) -> bool {
+        self.tabs.len() <= 1 && self.tabs.iter().all(TabDraft::is_empty)
+    }
+}
+
+impl TabDraft {
+    pub fn is_empty(&self) -> bool {
+        self.method.is_empty()
+            && self.uri.is_empty()
+            && self.body.is_empty()
+            && self.log_id.is_none()
+            && self.transfer.is_none()
+            && BodyState::default() == self.body_state
+            && self
+                .header
+                .iter()
+                .all(|(name, value)| name.is_empty() && value.is_empty())
+    }
+}
+
+/// The draft of this browser tab, kept over reloads. A new browser tab takes over the newest
+/// draft left by a closed one, once it could open it.
+pub async fn restore() -> Result<Restored, JsValue> {
+    let tab_id = tab_id().await;
+    let vault_key = unlocked_key().await;
+    if let Ok(stored) = get_local::<Stored>(&key(tab_id)).await {
+        return stored.open(vault_key.as_ref());
+    }
+
+    let open_tabs = open_tabs().await?;
+    let all = browser().storage().local().get(&JsValue::NULL).await?;
+    let Value::Object(all) = from_value::<Value>(all)? else {
+        return Ok(Restored::Nothing);
+    };
+    let orphan = all
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let owner = key.strip_prefix(DRAFT_PREFIX)?.parse::<i32>().ok()?;
+            let stored = serde_json::from_value::<Stored>(value).ok()?;
+            (!open_tabs.contains(&owner)).then_some((key, stored))
+        })
+        .max_by_key(|(_, stored)| stored.saved());
+    let Some((orphan_key, stored)) = orphan else {
+        return Ok(Restored::Nothing);
+    };
+    let restored = stored.clone().open(vault_key.as_ref())?;
+    if Restored::Locked == restored {
+        return Ok(restored);
+    }
+
+    set_local(&key(tab_id), &stored).await?;
+    browser()
+        .storage()
+        .local()
+        .remove(&JsValue::from_str(&orphan_key))
+        .await?;
+    Ok(restored)
+}
+
+/// Saves `draft` for this browser tab, or drops it if there is nothing in it. It is sealed
+/// while the history is encrypted, and masked by the redaction rules while it is locked.
+/// Files are left out.
+pub async fn save(mut draft: Draft) -> Result<(), JsValue> {
+    if draft.is_empty() {
+        return discard().await;
+    }
+    draft.saved = OffsetDateTime::now_utc();
+    for tab in draft.tabs.iter_mut() {
+        tab.body_state.drop_files();
+    }
+    let stored = match sealing_key().await {
+        Ok(Some(key)) => Stored::Sealed {
+            sealed: key.seal_value(&draft)?,
+            saved: draft.saved,
+        },
+        Ok(None) => Stored::Plain(draft),
+        Err(_) => {
+            let redaction = redaction().await;
+            for tab in draft.tabs.iter_mut() {
+                redaction.apply_draft(tab);
+            }
+            Stored::Plain(draft)
+        }
+    };
+    set_local(&key(tab_id().await), &stored).await
+}
+
+pub async fn discard() -> Result<(), JsValue> {
+    browser()
+        .storage()
+        .local()
+        .remove(&JsValue::from_str(&key(tab_id().await)))
+        .await?;
+    Ok(())
+}
+
+fn key(tab_id: i32) -> String {
+    format!("{DRAFT_PREFIX}{tab_id}")
+}
+
+async fn tab_id() -> i32 {
+    browser()
+        .tabs()
+        .get_current()
+        .await
+        .ok()
+        .filter(|tab| !tab.is_undefined())
+        .and_then(|tab| Tab::from(tab).id())
+        .unwrap_or(TAB_ID_NONE)
+}
+
+async fn open_tabs() -> Result<HashSet<i32>, JsValue> {
+    let tabs = browser().tabs().query(&Object::new()).await?;
+    Ok(Array::from(&tabs)
+        .iter()
+        .filter_map(|tab| Reflect::get(&tab, &JsValue::from_str("id")).ok())
+        .filter_map(|id| id.as_f64())
+        .map(|id| id as i32)
+        .collect())
+}
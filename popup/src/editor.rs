@@ -0,0 +1,816 @@
+//! Code editor of the raw body. The editing is done by a transparent `<textarea>` laid over the
+//! highlighted text, and only the lines in view are highlighted, so bodies of several MB stay
+//! responsive.
+
+use std::time::Duration;
+
+use leptos::{
+    html::{Input, Textarea},
+    prelude::*,
+};
+use serde::de::IgnoredAny;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlDocument, HtmlTextAreaElement, KeyboardEvent};
+
+use crate::body::Language;
+use crate::preview::format_size;
+use crate::syntax::tokenize;
+
+/// Height of a line in pixels, `leading-5`.
+const LINE_HEIGHT: f64 = 20.0;
+/// Left padding of the text in pixels, `pl-2`.
+const PADDING: f64 = 8.0;
+/// Lines rendered above and below the ones in view.
+const OVERSCAN: usize = 20;
+/// Longer lines are only highlighted up to here, a minified body is often a single line.
+const HIGHLIGHT_LIMIT: usize = 10_000;
+/// Bytes searched for the bracket pairing with the one at the caret, on either side of it.
+const BRACKET_LIMIT: usize = 100_000;
+/// Pause in typing before the JSON is validated.
+const VALIDATE_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Bytes compared at once by [`common_prefix`] and [`common_suffix`].
+const CHUNK: usize = 64;
+const INDENT: &str = "  ";
+
+#[component]
+pub fn CodeEditor(
+    value: RwSignal<String>,
+    #[prop(into)] language: Signal<Language>,
+) -> impl IntoView {
+    let textarea = NodeRef::<Textarea>::new();
+    let find_input = NodeRef::<Input>::new();
+    // Scroll top and left of the textarea, and the height of its viewport.
+    let scroll = RwSignal::new((0.0, 0.0));
+    let height = RwSignal::new(
+        window()
+            .inner_height()
+            .ok()
+            .and_then(|height| height.as_f64())
+            .unwrap_or(800.0),
+    );
+    // Byte offset of the caret.
+    let cursor = RwSignal::new(0usize);
+    // The text the line starts were last found in, to only search the part edited since.
+    let indexed = StoredValue::new(String::new());
+    let starts = Memo::new(move |previous: Option<&Vec<usize>>| {
+        value.with(|text| {
+            indexed
+                .try_update_value(|old| {
+                    let starts = match previous {
+                        Some(previous) => update_starts(previous.clone(), old, text),
+                        None => line_starts(text),
+                    };
+                    old.clone_from(text);
+                    starts
+                })
+                .unwrap_or_default()
+        })
+    });
+
+    let sync = move || {
+        let Some(area) = textarea.get_untracked() else {
+            return;
+        };
+        let caret = area.selection_start().ok().flatten().unwrap_or_default() as usize;
+        cursor.set(value.with_untracked(|text| byte_offset(text, caret)));
+        scroll.set((area.scroll_top() as f64, area.scroll_left() as f64));
+        if area.client_height() > 0 {
+            height.set(area.client_height() as f64);
+        }
+    };
+
+    // Counts the pauses in typing.
+    let settled = RwSignal::new(0usize);
+    let generation = StoredValue::new(0usize);
+    Effect::new(move |_| {
+        value.track();
+        generation.update_value(|generation| *generation += 1);
+        let current = generation.get_value();
+        set_timeout(
+            move || {
+                if current == generation.get_value() {
+                    settled.update(|settled| *settled += 1);
+                }
+            },
+            VALIDATE_DEBOUNCE,
+        );
+    });
+    let json_error = Memo::new(move |_| {
+        settled.track();
+        if Language::Json != language.get() {
+            return None;
+        }
+        value.with_untracked(|text| {
+            if text.trim().is_empty() {
+                return None;
+            }
+            serde_json::from_str::<IgnoredAny>(text).err().map(|e| {
+                let column = char_column(text, e.line(), e.column());
+                (e.line(), column, e.to_string())
+            })
+        })
+    });
+    let brackets = Memo::new(move |_| {
+        let at = cursor.get();
+        starts.with(|starts| value.with(|text| matching_bracket(text, starts, at)))
+    });
+
+    let finding = RwSignal::new(false);
+    let needle = RwSignal::new(String::new());
+    let replacement = RwSignal::new(String::new());
+    // Byte offset of the match selected last.
+    let found = RwSignal::new(None::<usize>);
+    let match_count = Memo::new(move |_| {
+        if !finding.get() {
+            return 0;
+        }
+        needle.with(|needle| {
+            if needle.is_empty() {
+                0
+            } else {
+                value.with(|text| text.matches(needle.as_str()).count())
+            }
+        })
+    });
+    let find_next = move || {
+        let Some(area) = textarea.get_untracked() else {
+            return;
+        };
+        let needle = needle.get_untracked();
+        if needle.is_empty() {
+            return;
+        }
+        let end = area.selection_end().ok().flatten().unwrap_or_default() as usize;
+        let start = value.with_untracked(|text| {
+            let from = byte_offset(text, end);
+            text[from..]
+                .find(&needle)
+                .map(|at| from + at)
+                .or_else(|| text.find(&needle))
+        });
+        found.set(start);
+        if let Some(start) = start {
+            value.with_untracked(|text| select(&area, text, start, start + needle.len()));
+            sync();
+        }
+    };
+    let replace = move || {
+        let Some(area) = textarea.get_untracked() else {
+            return;
+        };
+        let selected = value.with_untracked(|text| {
+            let start = area.selection_start().ok().flatten().unwrap_or_default() as usize;
+            let end = area.selection_end().ok().flatten().unwrap_or_default() as usize;
+            text[byte_offset(text, start)..byte_offset(text, end)].to_string()
+        });
+        if needle.with_untracked(|needle| !needle.is_empty() && *needle == selected) {
+            insert(&area, &replacement.get_untracked());
+        }
+        find_next();
+    };
+    let replace_all = move || {
+        let Some(area) = textarea.get_untracked() else {
+            return;
+        };
+        let needle = needle.get_untracked();
+        if needle.is_empty() {
+            return;
+        }
+        let replaced =
+            value.with_untracked(|text| text.replace(&needle, &replacement.get_untracked()));
+        area.select();
+        insert(&area, &replaced);
+        found.set(None);
+    };
+    let open_find = move || {
+        let Some(area) = textarea.get_untracked() else {
+            return;
+        };
+        let start = area.selection_start().ok().flatten().unwrap_or_default() as usize;
+        let end = area.selection_end().ok().flatten().unwrap_or_default() as usize;
+        let selected = value.with_untracked(|text| {
+            text[byte_offset(text, start)..byte_offset(text, end)].to_string()
+        });
+        if !selected.is_empty() && !selected.contains('\n') {
+            needle.set(selected);
+        }
+        finding.set(true);
+        if let Some(input) = find_input.get_untracked() {
+            let _ = input.focus();
+            input.select();
+        }
+    };
+
+    let reformat = move |pretty: bool| {
+        let Some(area) = textarea.get_untracked() else {
+            return;
+        };
+        if let Some(formatted) = value.with_untracked(|text| format_json(text, pretty)) {
+            area.select();
+            insert(&area, &formatted);
+        }
+    };
+    let can_format = move || {
+        Language::Json == language.get()
+            && json_error.with(Option::is_none)
+            && value.with(|text| !text.trim().is_empty())
+    };
+
+    let on_keydown = move |ev: KeyboardEvent| {
+        let Some(area) = textarea.get_untracked() else {
+            return;
+        };
+        let modifier = ev.ctrl_key() || ev.meta_key();
+        let plain = !modifier && !ev.shift_key() && !ev.alt_key();
+        match ev.key().as_str() {
+            "f" | "h" if modifier => {
+                ev.prevent_default();
+                open_find();
+            }
+            "Tab" if plain => {
+                ev.prevent_default();
+                insert(&area, INDENT);
+            }
+            "Enter" if plain => {
+                ev.prevent_default();
+                let caret = area.selection_start().ok().flatten().unwrap_or_default() as usize;
+                let indent = value.with_untracked(|text| {
+                    let at = byte_offset(text, caret);
+                    let line = &text[text[..at].rfind('\n').map_or(0, |at| at + 1)..at];
+                    let mut indent = line
+                        .chars()
+                        .take_while(|c| ' ' == *c || '\t' == *c)
+                        .collect::<String>();
+                    if line.trim_end().ends_with(['{', '[', '(']) {
+                        indent.push_str(INDENT);
+                    }
+                    indent
+                });
+                insert(&area, &format!("\n{indent}"));
+            }
+            "Escape" if finding.get_untracked() => finding.set(false),
+            _ => {}
+        }
+    };
+
+    // First line rendered and the lines after it.
+    let visible = move || {
+        let count = starts.with(Vec::len);
+        let first = ((scroll.get().0 / LINE_HEIGHT) as usize)
+            .saturating_sub(OVERSCAN)
+            .min(count);
+        let shown = (height.get() / LINE_HEIGHT) as usize + 2 * OVERSCAN;
+        first..(first + shown).min(count)
+    };
+    let code = move || {
+        let language = language.get();
+        let lines = visible();
+        starts.with(|starts| {
+            value.with(|text| {
+                lines
+                    .map(|line| {
+                        let end = starts.get(line + 1).map_or(text.len(), |next| next - 1);
+                        let source = &text[starts[line]..end];
+                        let source = source.strip_suffix('\r').unwrap_or(source);
+                        let cut = floor_boundary(source, HIGHLIGHT_LIMIT);
+                        let tokens = tokenize(language, &source[..cut])
+                            .into_iter()
+                            .map(|(token, text)| {
+                                view! { <span class=token.class()>{text.to_string()}</span> }
+                            })
+                            .collect_view();
+                        view! {
+                            <div class="h-5">{tokens}{source[cut..].to_string()}</div>
+                        }
+                    })
+                    .collect_view()
+            })
+        })
+    };
+    let gutter = move || {
+        let error_line = json_error.with(|error| error.as_ref().map(|(line, ..)| *line));
+        visible()
+            .map(|line| {
+                view! {
+                    <div class="h-5 pr-2" class:text-error=Some(line + 1) == error_line>
+                        {line + 1}
+                    </div>
+                }
+            })
+            .collect_view()
+    };
+    let marks = move || {
+        let mut marks = Vec::new();
+        starts.with(|starts| {
+            value.with(|text| {
+                if let Some((open, close)) = brackets.get() {
+                    for at in [open, close] {
+                        let (line, column) = position(text, starts, at);
+                        marks.push(mark(line, column, 1, "bg-base-content/20"));
+                    }
+                }
+                if let Some(start) = found.get().filter(|_| finding.get()) {
+                    needle.with(|needle| {
+                        if Some(needle.as_str()) == text.get(start..start + needle.len()) {
+                            let (line, column) = position(text, starts, start);
+                            marks.push(mark(line, column, needle.chars().count(), "bg-warning/40"));
+                        }
+                    });
+                }
+            })
+        });
+        if let Some((line, column, _)) = json_error.get() {
+            marks.push(mark(
+                line.saturating_sub(1),
+                column.saturating_sub(1),
+                1,
+                "bg-error/50",
+            ));
+        }
+        marks
+    };
+
+    view! {
+        <div class="flex h-full w-full flex-col">
+            <div class="flex items-center gap-1 py-1 text-xs">
+                <button
+                    class="btn btn-xs"
+                    title="Format JSON"
+                    disabled=move || !can_format()
+                    on:click=move |_| reformat(true)
+                >
+                    "Format"
+                </button>
+                <button
+                    class="btn btn-xs"
+                    title="Minify JSON"
+                    disabled=move || !can_format()
+                    on:click=move |_| reformat(false)
+                >
+                    "Minify"
+                </button>
+                <button
+                    class="btn btn-xs"
+                    class:btn-active=finding
+                    title="Find and replace (Ctrl+F)"
+                    on:click=move |_| {
+                        if finding.get_untracked() {
+                            finding.set(false);
+                        } else {
+                            open_find();
+                        }
+                    }
+                >
+                    "Find"
+                </button>
+                <span class="ml-auto truncate">
+                    {move || {
+                        json_error
+                            .get()
+                            .map(|(line, column, message)| {
+                                view! {
+                                    <span class="text-error" title=message>
+                                        {format!("Invalid JSON at line {line}, column {column}")}
+                                    </span>
+                                }
+                                    .into_any()
+                            })
+                            .unwrap_or_else(|| {
+                                if Language::Json == language.get()
+                                    && value.with(|text| !text.trim().is_empty())
+                                {
+                                    view! { <span class="text-success">"Valid JSON"</span> }.into_any()
+                                } else {
+                                    ().into_any()
+                                }
+                            })
+                    }}
+                </span>
+                <span class="opacity-50">
+                    {move || {
+                        format!(
+                            "{} lines, {}",
+                            starts.with(Vec::len),
+                            value.with(|text| format_size(text.len())),
+                        )
+                    }}
+                </span>
+            </div>
+            <div class="join w-full pb-1" class:hidden=move || !finding.get()>
+                <input
+                    type="text"
+                    class="input input-xs join-item w-full"
+                    placeholder="Find..."
+                    node_ref=find_input
+                    prop:value=needle
+                    on:input=move |ev| {
+                        needle.set(event_target_value(&ev));
+                        found.set(None);
+                    }
+                    on:keydown=move |ev: KeyboardEvent| {
+                        match ev.key().as_str() {
+                            "Enter" => {
+                                ev.prevent_default();
+                                find_next();
+                            }
+                            "Escape" => finding.set(false),
+                            _ => {}
+                        }
+                    }
+                />
+                <input
+                    type="text"
+                    class="input input-xs join-item w-full"
+                    placeholder="Replace with..."
+                    prop:value=replacement
+                    on:input=move |ev| replacement.set(event_target_value(&ev))
+                />
+                <span class="join-item flex items-center px-2 text-xs opacity-50">
+                    {match_count}
+                </span>
+                <button class="btn btn-xs join-item" on:click=move |_| find_next()>
+                    "Next"
+                </button>
+                <button
+                    class="btn btn-xs join-item"
+                    on:click=move |_| replace()
+                >
+                    "Replace"
+                </button>
+                <button
+                    class="btn btn-xs join-item"
+                    on:click=move |_| replace_all()
+                >
+                    "All"
+                </button>
+            </div>
+            <div class="relative h-[60vh] w-full overflow-hidden border border-base-content/20 font-mono text-sm leading-5">
+                <div class="absolute inset-y-0 left-0 w-12 select-none overflow-hidden bg-base-200 text-right opacity-60">
+                    <div style:transform=move || {
+                        let first = visible().start as f64 * LINE_HEIGHT;
+                        format!("translateY({}px)", first - scroll.get().0)
+                    }>{gutter}</div>
+                </div>
+                <div class="pointer-events-none absolute inset-y-0 left-12 right-0 overflow-hidden whitespace-pre">
+                    <div
+                        class="relative"
+                        style:transform=move || {
+                            let (top, left) = scroll.get();
+                            format!("translate({}px, {}px)", -left, -top)
+                        }
+                    >
+                        {marks}
+                        <div
+                            class="absolute left-0 pl-2"
+                            style:top=move || {
+                                format!("{}px", visible().start as f64 * LINE_HEIGHT)
+                            }
+                        >
+                            {code}
+                        </div>
+                    </div>
+                </div>
+                <textarea
+                    class="absolute left-12 top-0 h-full w-[calc(100%-3rem)] resize-none overflow-auto whitespace-pre border-0 bg-transparent p-0 pl-2 font-mono text-sm leading-5 text-transparent caret-base-content outline-none"
+                    wrap="off"
+                    spellcheck="false"
+                    node_ref=textarea
+                    prop:value=value
+                    on:input=move |ev| {
+                        value.set(event_target_value(&ev));
+                        sync();
+                    }
+                    on:keydown=on_keydown
+                    on:keyup=move |_| sync()
+                    on:click=move |_| sync()
+                    on:focus=move |_| sync()
+                    on:select=move |_| sync()
+                    on:scroll=move |_| sync()
+                ></textarea>
+            </div>
+        </div>
+    }
+}
+
+/// A highlight box over `width` characters from `column` of `line`.
+fn mark(line: usize, column: usize, width: usize, class: &'static str) -> impl IntoView {
+    view! {
+        <div
+            class=format!("absolute h-5 rounded-sm {class}")
+            style:top=format!("{}px", line as f64 * LINE_HEIGHT)
+            style:left=format!("calc({PADDING}px + {column}ch)")
+            style:width=format!("{width}ch")
+        ></div>
+    }
+}
+
+/// Replaces the selection of `area` with `text` the way typing does, so it can be undone.
+fn insert(area: &HtmlTextAreaElement, text: &str) {
+    let _ = area.focus();
+    let inserted = document()
+        .unchecked_into::<HtmlDocument>()
+        .exec_command_with_show_ui_and_value("insertText", false, text)
+        .unwrap_or_default();
+    if !inserted {
+        let _ = area.set_range_text_with_start_and_end_and_mode(
+            text,
+            area.selection_start().ok().flatten().unwrap_or_default(),
+            area.selection_end().ok().flatten().unwrap_or_default(),
+            "end",
+        );
+        if let Ok(event) = Event::new("input") {
+            let _ = area.dispatch_event(&event);
+        }
+    }
+}
+
+/// Selects the bytes `start..end` of `text` in `area` and scrolls them into the middle of it.
+fn select(area: &HtmlTextAreaElement, text: &str, start: usize, end: usize) {
+    let _ = area.set_selection_range(utf16_offset(text, start), utf16_offset(text, end));
+    let line = text[..start].matches('\n').count() as f64;
+    let top = line * LINE_HEIGHT - area.client_height() as f64 / 2.0;
+    area.set_scroll_top(top.max(0.0) as i32);
+}
+
+/// Byte offset of each line start.
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(at, _)| at + 1))
+        .collect()
+}
+
+/// [`line_starts`] of `text`, from the `starts` of the `old` text it was edited from. Only the
+/// bytes between the first and the last that changed are searched.
+fn update_starts(mut starts: Vec<usize>, old: &str, text: &str) -> Vec<usize> {
+    let (old, new) = (old.as_bytes(), text.as_bytes());
+    let prefix = common_prefix(old, new);
+    let suffix = common_suffix(&old[prefix..], &new[prefix..]);
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    // A start follows a newline, those before the edit stay and those after it move.
+    let kept = starts.partition_point(|start| *start <= prefix);
+    let moved = starts.partition_point(|start| *start <= old_end);
+    let tail = starts[moved..]
+        .iter()
+        .map(|start| start - old_end + new_end)
+        .collect::<Vec<_>>();
+    starts.truncate(kept);
+    starts.extend(
+        new[prefix..new_end]
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b'\n' == **b)
+            .map(|(at, _)| prefix + at + 1),
+    );
+    starts.extend(tail);
+    starts
+}
+
+/// Bytes `a` and `b` start with in common, compared a chunk at a time first.
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    let chunks = a
+        .chunks(CHUNK)
+        .zip(b.chunks(CHUNK))
+        .take_while(|(a, b)| a == b)
+        .count();
+    let at = (chunks * CHUNK).min(a.len()).min(b.len());
+    at + a[at..]
+        .iter()
+        .zip(&b[at..])
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Bytes `a` and `b` end with in common.
+fn common_suffix(a: &[u8], b: &[u8]) -> usize {
+    let chunks = a
+        .rchunks(CHUNK)
+        .zip(b.rchunks(CHUNK))
+        .take_while(|(a, b)| a == b)
+        .count();
+    let at = (chunks * CHUNK).min(a.len()).min(b.len());
+    at + a[..a.len() - at]
+        .iter()
+        .rev()
+        .zip(b[..b.len() - at].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Line and column, in characters, of the byte `offset` of `text`.
+fn position(text: &str, starts: &[usize], offset: usize) -> (usize, usize) {
+    let line = starts.partition_point(|start| *start <= offset) - 1;
+    (line, text[starts[line]..offset].chars().count())
+}
+
+/// Byte offset of the UTF-16 offset `units`, which is how the textarea counts.
+fn byte_offset(text: &str, units: usize) -> usize {
+    let mut counted = 0;
+    for (at, c) in text.char_indices() {
+        if counted >= units {
+            return at;
+        }
+        counted += c.len_utf16();
+    }
+    text.len()
+}
+
+fn utf16_offset(text: &str, offset: usize) -> u32 {
+    text[..offset].encode_utf16().count() as u32
+}
+
+/// Largest char boundary of `text` not past `limit`.
+fn floor_boundary(text: &str, limit: usize) -> usize {
+    if limit >= text.len() {
+        return text.len();
+    }
+    (0..=limit)
+        .rev()
+        .find(|at| text.is_char_boundary(*at))
+        .unwrap_or_default()
+}
+
+/// serde_json's 1-based byte `column` of the 1-based `line` of `text`, counted in characters.
+fn char_column(text: &str, line: usize, column: usize) -> usize {
+    let Some(source) = text.split('\n').nth(line.saturating_sub(1)) else {
+        return column;
+    };
+    match column {
+        0 => 0,
+        column => source[..floor_boundary(source, column - 1)].chars().count() + 1,
+    }
+}
+
+/// Byte offsets of the bracket at or just before `at` and of the one it pairs with, `starts`
+/// being the line starts of `text`. Brackets in double-quoted strings don't count, and the
+/// search stops [`BRACKET_LIMIT`] bytes away.
+fn matching_bracket(text: &str, starts: &[usize], at: usize) -> Option<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let from = [Some(at), at.checked_sub(1)]
+        .into_iter()
+        .flatten()
+        .find(|at| bytes.get(*at).is_some_and(|b| b"{}[]()".contains(b)))?;
+    // Strings don't span lines, so a line start is outside of one.
+    let low = from.saturating_sub(BRACKET_LIMIT);
+    let low = starts[starts.partition_point(|start| *start <= low) - 1];
+    if from - low > 2 * BRACKET_LIMIT {
+        return None;
+    }
+    let high = bytes.len().min(from + BRACKET_LIMIT + 1);
+    let mut code = Vec::new();
+    let (mut quoted, mut escaped) = (false, false);
+    for (at, b) in bytes[low..high].iter().enumerate() {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' if quoted => escaped = true,
+            b'"' => quoted = !quoted,
+            b'\n' => quoted = false,
+            b'{' | b'}' | b'[' | b']' | b'(' | b')' if !quoted => code.push(low + at),
+            _ => {}
+        }
+    }
+    let index = code.binary_search(&from).ok()?;
+    let bracket = bytes[from];
+    let (pair, forward) = match bracket {
+        b'{' => (b'}', true),
+        b'[' => (b']', true),
+        b'(' => (b')', true),
+        b'}' => (b'{', false),
+        b']' => (b'[', false),
+        _ => (b'(', false),
+    };
+    let mut depth = 0usize;
+    let mut step = |at: usize| {
+        if bracket == bytes[at] {
+            depth += 1;
+        } else if pair == bytes[at] {
+            depth -= 1;
+            if 0 == depth {
+                return true;
+            }
+        }
+        false
+    };
+    let to = if forward {
+        code[index..].iter().copied().find(|at| step(*at))
+    } else {
+        code[..=index].iter().copied().rev().find(|at| step(*at))
+    }?;
+    Some((from, to))
+}
+
+/// `json` laid out again with the order of its keys kept, on one line each value when `pretty`,
+/// with no whitespace at all otherwise. `None` if it is not valid JSON.
+fn format_json(json: &str, pretty: bool) -> Option<String> {
+    serde_json::from_str::<IgnoredAny>(json).ok()?;
+    let newline = |out: &mut String, depth: usize| {
+        if pretty {
+            out.push('\n');
+            out.push_str(&INDENT.repeat(depth));
+        }
+    };
+    let mut out = String::with_capacity(json.len());
+    let mut depth = 0;
+    let mut chars = json.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    out.push(c);
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '[' => {
+                out.push(c);
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                if chars.peek().is_some_and(|next| matches!(next, '}' | ']')) {
+                    out.extend(chars.next());
+                } else {
+                    depth += 1;
+                    newline(&mut out, depth);
+                }
+            }
+            '}' | ']' => {
+                depth -= 1;
+                newline(&mut out, depth);
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                newline(&mut out, depth);
+            }
+            ':' => {
+                out.push(c);
+                if pretty {
+                    out.push(' ');
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_json_keeps_key_order_and_strings() {
+        let json = r#"{ "b": [1, 2, {}], "a": "x , {\"y\": []}", "c": [ ] }"#;
+        assert_eq!(
+            format_json(json, true).as_deref(),
+            Some(
+                "{\n  \"b\": [\n    1,\n    2,\n    {}\n  ],\n  \"a\": \"x , {\\\"y\\\": []}\",\n  \"c\": []\n}"
+            )
+        );
+        assert_eq!(
+            format_json(json, false).as_deref(),
+            Some(r#"{"b":[1,2,{}],"a":"x , {\"y\": []}","c":[]}"#)
+        );
+        assert_eq!(format_json("{\"a\": }", true), None);
+    }
+
+    #[test]
+    fn matching_bracket_skips_strings() {
+        let text = "{\"a\": \"}\",\n \"b\": [1, (2)]}";
+        let starts = line_starts(text);
+        let close = text.len() - 1;
+        assert_eq!(matching_bracket(text, &starts, 0), Some((0, close)));
+        // Just after a bracket counts too.
+        assert_eq!(matching_bracket(text, &starts, close + 1), Some((close, 0)));
+        let open = text.find('[').unwrap();
+        assert_eq!(
+            matching_bracket(text, &starts, open),
+            Some((open, close - 1))
+        );
+        assert_eq!(matching_bracket(text, &starts, 2), None);
+        assert_eq!(matching_bracket("(]", &line_starts("(]"), 0), None);
+    }
+
+    #[test]
+    fn update_starts_matches_a_full_scan() {
+        let edits = [
+            ("a\nb\nc", "a\nb\nc\n"),
+            ("a\nb\nc", "a\nXY\nZ\nb\nc"),
+            ("a\nb\nc", "ac"),
+            ("a\n\n\nb", "a\n\nb"),
+            ("", "\n\n"),
+            ("a\nb", ""),
+        ];
+        for (old, text) in edits {
+            assert_eq!(
+                update_starts(line_starts(old), old, text),
+                line_starts(text),
+                "{old:?} -> {text:?}"
+            );
+        }
+    }
+}
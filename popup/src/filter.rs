@@ -0,0 +1,65 @@
+use jaq_core::{
+    compile::Undefined,
+    load::{self, Arena, File, Loader},
+    Compiler, Ctx, RcIter,
+};
+use jaq_json::Val;
+use serde_json::Value;
+
+/// The jq input for `value`, converted once per response and cheap to clone afterwards.
+pub fn input(value: &Value) -> Val {
+    Val::from(value.clone())
+}
+
+/// Runs the jq program `code` over `input`, returning every output value.
+pub fn run(code: &str, input: &Val) -> Result<Vec<Value>, String> {
+    let program = File { code, path: () };
+    let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+    let arena = Arena::default();
+
+    let modules = loader
+        .load(&arena, program)
+        .map_err(|errors| load_error(code, errors))?;
+    let filter = Compiler::default()
+        .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+        .compile(modules)
+        .map_err(compile_error)?;
+
+    let inputs = RcIter::new(core::iter::empty());
+    filter
+        .run((Ctx::new([], &inputs), input.clone()))
+        .map(|output| output.map(Value::from).map_err(|e| e.to_string()))
+        .collect()
+}
+
+fn load_error(code: &str, errors: load::Errors<&str, ()>) -> String {
+    let column = |at: &str| at.as_ptr() as usize - code.as_ptr() as usize + 1;
+    let mut messages = Vec::new();
+    for (_, error) in errors {
+        match error {
+            load::Error::Io(errors) => {
+                messages.extend(errors.into_iter().map(|(path, e)| format!("{path}: {e}")))
+            }
+            load::Error::Lex(errors) => messages.extend(errors.into_iter().map(|(expect, at)| {
+                format!("expected {} at column {}", expect.as_str(), column(at))
+            })),
+            load::Error::Parse(errors) => {
+                messages.extend(errors.into_iter().map(|(expect, at)| {
+                    format!("expected {} at column {}", expect.as_str(), column(at))
+                }))
+            }
+        }
+    }
+    messages.join("\n")
+}
+
+fn compile_error(errors: jaq_core::compile::Errors<&str, ()>) -> String {
+    errors
+        .into_iter()
+        .flat_map(|(_, errors)| errors)
+        .map(|(name, undefined): (&str, Undefined)| {
+            format!("undefined {} `{name}`", undefined.as_str())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,295 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use http::{StatusCode, Uri};
+use serde::Serialize;
+use time::{format_description::well_known::Rfc3339, macros::format_description, OffsetDateTime};
+use wasm_bindgen::JsValue;
+
+use crate::browser::{download, js_error};
+use crate::log::{LogContent, LogIndexItem};
+use crate::vault::get_content;
+
+/// HTTP Archive 1.2, http://www.softwareishard.com/blog/har-12-spec/
+#[derive(Debug, Serialize)]
+struct Har {
+    log: Log,
+}
+
+#[derive(Debug, Serialize)]
+struct Log {
+    version: &'static str,
+    creator: Creator,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: String,
+    time: i32,
+    request: HarRequest,
+    response: HarResponse,
+    cache: Cache,
+    timings: Timings,
+    /// Tags and note of the history item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    #[serde(rename = "_tags", skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Why the send failed, the response is then empty with status 0.
+    #[serde(rename = "_error", skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: &'static str,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: &'static str,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    text: String,
+    /// Not in HAR 1.2, set like [`Content::encoding`] for a body that is not UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: usize,
+    mime_type: String,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct Cache {}
+
+/// Only the total time is known, it is all put on `wait`.
+#[derive(Debug, Serialize)]
+struct Timings {
+    send: i32,
+    wait: i32,
+    receive: i32,
+}
+
+/// Downloads `items` with their contents as a HAR file.
+pub async fn export(items: Vec<LogIndexItem>) -> Result<(), JsValue> {
+    let mut entries = Vec::with_capacity(items.len());
+    for item in items {
+        let content = get_content(&item.id.to_string()).await?;
+        if let Some(content) = content {
+            entries.push(entry(item, content)?);
+        }
+    }
+    let har = Har {
+        log: Log {
+            version: "1.2",
+            creator: Creator {
+                name: "CDGHOPT",
+                version: env!("CARGO_PKG_VERSION"),
+            },
+            entries,
+        },
+    };
+
+    let json = serde_json::to_vec_pretty(&har).map_err(js_error)?;
+    let now = OffsetDateTime::now_utc()
+        .format(format_description!(
+            "[year][month][day]-[hour][minute][second]"
+        ))
+        .map_err(js_error)?;
+    download(&json, "application/json", &format!("cdghopt-{now}.har"))
+}
+
+fn entry(item: LogIndexItem, content: LogContent) -> Result<Entry, JsValue> {
+    let LogContent {
+        request, response, ..
+    } = content;
+    let mut request_headers = request
+        .header
+        .into_iter()
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, value)| NameValue { name, value })
+        .collect::<Vec<_>>();
+    request_headers.sort_by(|a, b| a.name.cmp(&b.name));
+    let request_type = header_value(&request_headers, "content-type");
+    let body_size = request.body.len() as i64;
+    let post_data = (!request.body.is_empty()).then(|| {
+        let (text, encoding) = text(request.body);
+        PostData {
+            mime_type: request_type.unwrap_or_default(),
+            text,
+            encoding,
+        }
+    });
+
+    let response = match response {
+        Some(response) => {
+            let mut headers = response
+                .header
+                .into_iter()
+                .map(|(name, value)| NameValue {
+                    name,
+                    value: String::from_utf8_lossy(&value).into_owned(),
+                })
+                .collect::<Vec<_>>();
+            headers.sort_by(|a, b| a.name.cmp(&b.name));
+            let mime_type = header_value(&headers, "content-type").unwrap_or_default();
+            let redirect_url = header_value(&headers, "location").unwrap_or_default();
+            let size = response.body.len();
+            let (text, encoding) = text(response.body);
+            HarResponse {
+                status: response.status,
+                status_text: StatusCode::from_u16(response.status)
+                    .ok()
+                    .and_then(|status| status.canonical_reason())
+                    .unwrap_or_default()
+                    .to_string(),
+                http_version: "HTTP/1.1",
+                cookies: Vec::new(),
+                headers,
+                content: Content {
+                    size,
+                    mime_type,
+                    text,
+                    encoding,
+                },
+                redirect_url,
+                headers_size: -1,
+                body_size: size as i64,
+            }
+        }
+        None => HarResponse {
+            status: 0,
+            status_text: String::new(),
+            http_version: "",
+            cookies: Vec::new(),
+            headers: Vec::new(),
+            content: Content {
+                size: 0,
+                mime_type: String::new(),
+                text: String::new(),
+                encoding: None,
+            },
+            redirect_url: String::new(),
+            headers_size: -1,
+            body_size: -1,
+        },
+    };
+
+    Ok(Entry {
+        // The date a send started, taken before it went out.
+        started_date_time: item.done_date.format(&Rfc3339).map_err(js_error)?,
+        time: item.elapsed_time,
+        request: HarRequest {
+            method: request.method,
+            query_string: query_string(&request.uri),
+            url: request.uri,
+            http_version: "HTTP/1.1",
+            cookies: Vec::new(),
+            headers: request_headers,
+            post_data,
+            headers_size: -1,
+            body_size,
+        },
+        response,
+        cache: Cache {},
+        timings: Timings {
+            send: 0,
+            wait: item.elapsed_time,
+            receive: 0,
+        },
+        comment: comment(&item),
+        tags: item.tags,
+        error: item.error,
+    })
+}
+
+/// `body` as HAR text, base64 encoded unless it is UTF-8.
+fn text(body: Vec<u8>) -> (String, Option<&'static str>) {
+    match String::from_utf8(body) {
+        Ok(text) => (text, None),
+        Err(e) => (STANDARD.encode(e.into_bytes()), Some("base64")),
+    }
+}
+
+/// Tags and note of `item` as one HAR comment.
+fn comment(item: &LogIndexItem) -> Option<String> {
+    let mut parts = Vec::new();
+    if !item.tags.is_empty() {
+        parts.push(format!("Tags: {}", item.tags.join(", ")));
+    }
+    if !item.note.is_empty() {
+        parts.push(item.note.clone());
+    }
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
+fn header_value(headers: &[NameValue], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case(name))
+        .map(|header| header.value.clone())
+}
+
+fn query_string(uri: &str) -> Vec<NameValue> {
+    let Some(query) = uri
+        .parse::<Uri>()
+        .ok()
+        .and_then(|uri| uri.query().map(str::to_string))
+    else {
+        return Vec::new();
+    };
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            NameValue {
+                name: name.to_string(),
+                value: value.to_string(),
+            }
+        })
+        .collect()
+}
@@ -1,4 +1,5 @@
 use leptos::prelude::*;
+use module::forbidden::{self, Forbidden};
 use thaw::{AutoComplete, AutoCompleteOption};
 use thaw_utils::BoxOneCallback;
 
@@ -71,7 +72,7 @@ pub fn HeaderTable(rows: RwSignal<Vec<(String, String)>>, class: &'static str) -
                                     <input
                                         type="text"
                                         placeholder="value..."
-                                        prop:value=row.get().1
+                                        prop:value=move || row.get().1
                                         on:focusout=move |ev| {
                                             rows.update(|rows| {
                                                 let row = rows.get_mut(index).unwrap();
@@ -81,6 +82,33 @@ pub fn HeaderTable(rows: RwSignal<Vec<(String, String)>>, class: &'static str) -
                                         class="input input-sm rounded-none w-full"
                                     />
                                 </td>
+                                <td class="w-10">
+                                    {move || match forbidden::header(&row.get().0) {
+                                        Some(Forbidden::Rule) => {
+                                            view! {
+                                                <span
+                                                    class="badge badge-info badge-sm"
+                                                    title="Browsers drop this header from fetch, it is set through a session declarativeNetRequest rule for this request only."
+                                                >
+                                                    "rule"
+                                                </span>
+                                            }
+                                                .into_any()
+                                        }
+                                        Some(Forbidden::Never) => {
+                                            view! {
+                                                <span
+                                                    class="badge badge-warning badge-sm"
+                                                    title="The browser's network stack writes this header itself, the value typed is not sent."
+                                                >
+                                                    "dropped"
+                                                </span>
+                                            }
+                                                .into_any()
+                                        }
+                                        None => ().into_any(),
+                                    }}
+                                </td>
                                 <th class="h-6 w-6">
                                     <Show when=move || !is_last>
                                         <svg
@@ -144,6 +172,8 @@ const HEADERS: &[&str] = &[
     "content-type",
     "cookie",
     "date",
+    "depth",
+    "destination",
     "dnt",
     "etag",
     "expect",
@@ -159,8 +189,10 @@ const HEADERS: &[&str] = &[
     "last-modified",
     "link",
     "location",
+    "lock-token",
     "max-forwards",
     "origin",
+    "overwrite",
     "pragma",
     "proxy-authenticate",
     "proxy-authorization",
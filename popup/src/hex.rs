@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use leptos::prelude::*;
+
+use crate::preview::format_size;
+
+const BYTES_PER_LINE: usize = 16;
+const PAGE_SIZE: usize = 4096;
+
+#[component]
+pub fn HexView(body: Arc<Vec<u8>>) -> impl IntoView {
+    let len = body.len();
+    let pages = len.div_ceil(PAGE_SIZE).max(1);
+    let page = RwSignal::new(0);
+
+    let lines = move || {
+        let start = page.get() * PAGE_SIZE;
+        let end = (start + PAGE_SIZE).min(len);
+        body[start..end]
+            .chunks(BYTES_PER_LINE)
+            .enumerate()
+            .map(|(i, chunk)| hex_line(start + i * BYTES_PER_LINE, chunk))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    view! {
+        <div class="flex items-center gap-2 px-4 text-xs">
+            <div class="join">
+                <button
+                    class="join-item btn btn-xs"
+                    disabled=move || 0 == page.get()
+                    on:click=move |_| page.update(|page| *page -= 1)
+                >
+                    "«"
+                </button>
+                <button class="join-item btn btn-xs">
+                    {move || format!("Page {} / {pages}", page.get() + 1)}
+                </button>
+                <button
+                    class="join-item btn btn-xs"
+                    disabled=move || page.get() + 1 >= pages
+                    on:click=move |_| page.update(|page| *page += 1)
+                >
+                    "»"
+                </button>
+            </div>
+            <input
+                type="text"
+                placeholder="go to offset (hex)"
+                class="input input-xs rounded-none font-mono w-36"
+                on:change=move |ev| {
+                    let offset = event_target_value(&ev);
+                    if let Ok(offset) = usize::from_str_radix(offset.trim_start_matches("0x"), 16) {
+                        page.set((offset / PAGE_SIZE).min(pages - 1));
+                    }
+                }
+            />
+            <span class="opacity-70">{format_size(len)}</span>
+        </div>
+        <pre class="p-4 rounded-md w-full overflow-x-auto">
+            <code>{lines}</code>
+        </pre>
+    }
+}
+
+/// `offset  hex bytes  |ascii|`, in the style of `hexdump -C`.
+fn hex_line(offset: usize, chunk: &[u8]) -> String {
+    let mut hex = String::with_capacity(BYTES_PER_LINE * 3 + 1);
+    for i in 0..BYTES_PER_LINE {
+        if BYTES_PER_LINE / 2 == i {
+            hex.push(' ');
+        }
+        match chunk.get(i) {
+            Some(b) => hex.push_str(&format!("{b:02x} ")),
+            None => hex.push_str("   "),
+        }
+    }
+    let ascii: String = chunk
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b' ' == b {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    format!("{offset:08x}  {hex} |{ascii}|")
+}
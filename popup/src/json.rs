@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use leptos::prelude::*;
+use serde_json::Value;
+
+use crate::browser::copy_text;
+
+/// Children rendered per "show more" click, keeps huge arrays from freezing the popup.
+const PAGE_SIZE: usize = 100;
+
+#[component]
+pub fn JsonTree(value: Arc<Value>) -> impl IntoView {
+    view! {
+        <ul class="p-4 font-mono text-sm w-full overflow-x-auto">
+            <JsonNode root=value pointer=String::new() path="$".to_string() label=None expanded=true />
+        </ul>
+    }
+}
+
+/// One node of the tree, located in `root` by its JSON pointer so that nothing is cloned
+/// until the node is actually expanded.
+#[component]
+fn JsonNode(
+    root: Arc<Value>,
+    pointer: String,
+    path: String,
+    label: Option<String>,
+    #[prop(optional)] expanded: bool,
+) -> impl IntoView {
+    let Some(value) = root.pointer(&pointer) else {
+        return ().into_any();
+    };
+
+    let label = label.map(|label| {
+        view! {
+            <span class="text-primary">{label}</span>
+            <span class="opacity-50">": "</span>
+        }
+    });
+
+    let count = match value {
+        Value::Array(array) => Some(array.len()),
+        Value::Object(object) => Some(object.len()),
+        _ => None,
+    };
+
+    let Some(count) = count else {
+        let (color, text) = leaf(value);
+        return view! {
+            <li class="group pl-5">
+                {label}
+                <span class=format!("break-all {color}")>{text}</span>
+                <CopyActions root=root pointer=pointer path=path />
+            </li>
+        }
+        .into_any();
+    };
+
+    let is_array = value.is_array();
+    let (open, close) = if is_array { ("[", "]") } else { ("{", "}") };
+    let unit = match (is_array, count) {
+        (true, 1) => "item",
+        (true, _) => "items",
+        (false, 1) => "key",
+        (false, _) => "keys",
+    };
+
+    let expanded = RwSignal::new(expanded);
+    // Every page is its own keyed chunk, showing more leaves the expanded children as they are.
+    let pages = RwSignal::new(1usize);
+
+    let children = {
+        let root = root.clone();
+        let pointer = pointer.clone();
+        let path = path.clone();
+        move || {
+            let root = root.clone();
+            let pointer = pointer.clone();
+            let path = path.clone();
+            view! {
+                <ul class="pl-4 ml-1 border-l border-base-content/10">
+                    <For
+                        each=move || 0..pages.get().min(count.div_ceil(PAGE_SIZE))
+                        key=|page| *page
+                        children=move |page| {
+                            child_entries(&root, &pointer, &path, page * PAGE_SIZE, PAGE_SIZE)
+                                .into_iter()
+                                .map(|(child_pointer, child_path, child_label)| {
+                                    view! {
+                                        <JsonNode
+                                            root=root.clone()
+                                            pointer=child_pointer
+                                            path=child_path
+                                            label=Some(child_label)
+                                        />
+                                    }
+                                    .into_any()
+                                })
+                                .collect_view()
+                        }
+                    />
+                    {move || {
+                        let remaining = count.saturating_sub(pages.get() * PAGE_SIZE);
+                        (remaining > 0)
+                            .then(|| {
+                                view! {
+                                    <li class="pl-5">
+                                        <button
+                                            class="btn btn-xs btn-ghost"
+                                            on:click=move |_| pages.update(|pages| *pages += 1)
+                                        >
+                                            {format!("show more ({remaining} remaining)")}
+                                        </button>
+                                    </li>
+                                }
+                            })
+                    }}
+                </ul>
+            }
+        }
+    };
+
+    view! {
+        <li class="group">
+            <span
+                class="cursor-pointer select-none"
+                on:click=move |_| expanded.update(|expanded| *expanded = !*expanded)
+            >
+                <span class="inline-block w-5 opacity-50">
+                    {move || if expanded.get() { "▾" } else { "▸" }}
+                </span>
+                {label}
+                <span class="opacity-50">{open}</span>
+                <Show when=move || !expanded.get()>
+                    <span class="opacity-50">"…"{close}</span>
+                </Show>
+                <span class="badge badge-ghost badge-xs ml-1">{format!("{count} {unit}")}</span>
+            </span>
+            <CopyActions root=root pointer=pointer path=path />
+            <Show when=move || expanded.get()>
+                {children.clone()}
+                <span class="pl-5 opacity-50">{close}</span>
+            </Show>
+        </li>
+    }
+    .into_any()
+}
+
+#[component]
+fn CopyActions(root: Arc<Value>, pointer: String, path: String) -> impl IntoView {
+    view! {
+        <span class="invisible group-hover:visible ml-2">
+            <button
+                class="btn btn-xs btn-ghost"
+                title=path.clone()
+                on:click=move |ev| {
+                    ev.stop_propagation();
+                    copy_text(&path);
+                }
+            >
+                path
+            </button>
+            <button
+                class="btn btn-xs btn-ghost"
+                on:click=move |ev| {
+                    ev.stop_propagation();
+                    if let Some(value) = root.pointer(&pointer) {
+                        copy_text(&serde_json::to_string_pretty(value).unwrap_or_default());
+                    }
+                }
+            >
+                value
+            </button>
+        </span>
+    }
+}
+
+/// Pointer, display path and label of `take` children of the node at `pointer`, from the
+/// `skip`th one.
+fn child_entries(
+    root: &Value,
+    pointer: &str,
+    path: &str,
+    skip: usize,
+    take: usize,
+) -> Vec<(String, String, String)> {
+    match root.pointer(pointer) {
+        Some(Value::Array(array)) => (skip.min(array.len())..array.len().min(skip + take))
+            .map(|i| {
+                (
+                    format!("{pointer}/{i}"),
+                    format!("{path}[{i}]"),
+                    i.to_string(),
+                )
+            })
+            .collect(),
+        Some(Value::Object(object)) => object
+            .keys()
+            .skip(skip)
+            .take(take)
+            .map(|key| {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                (
+                    format!("{pointer}/{escaped}"),
+                    child_path(path, key),
+                    key.clone(),
+                )
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    let mut chars = key.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        format!("{path}.{key}")
+    } else {
+        format!("{path}[{}]", serde_json::to_string(key).unwrap_or_default())
+    }
+}
+
+fn leaf(value: &Value) -> (&'static str, String) {
+    match value {
+        Value::String(s) => ("text-success", serde_json::to_string(s).unwrap_or_default()),
+        Value::Number(n) => ("text-info", n.to_string()),
+        Value::Bool(b) => ("text-warning", b.to_string()),
+        Value::Null => ("text-error", "null".to_string()),
+        _ => ("", String::new()),
+    }
+}
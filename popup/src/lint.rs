@@ -0,0 +1,387 @@
+//! Checks of a request about to be sent, for mistakes the server would answer with a confusing
+//! error or not at all.
+
+use leptos::prelude::*;
+use module::forbidden::{self, Forbidden};
+use module::http::Request;
+use serde::de::IgnoredAny;
+
+use crate::body::{BodyState, Language, Mode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// Fetch refuses a body on `GET` and `HEAD`.
+    BodyOnGet { method: String },
+    /// The `Content-Type` header names another type than the one the body is written in.
+    ContentType { header: String, body: String },
+    InvalidJson {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The browser computes `Content-Length` itself.
+    ContentLength { typed: String, actual: usize },
+    /// A header fetch won't let through, see [`Forbidden::Never`].
+    DroppedHeader { name: String },
+    /// A method fetch throws on.
+    ForbiddenMethod { method: String },
+    /// Headers set through a rule, see [`Forbidden::Rule`], on a URL the rule may not take.
+    LongRuleUrl { names: String },
+}
+
+impl Warning {
+    pub fn message(&self) -> String {
+        match self {
+            Self::BodyOnGet { method } => format!(
+                "{method} requests can't carry a body in the browser, the send will fail. Servers taking a body for a search usually take POST too."
+            ),
+            Self::ContentType { header, body } => {
+                format!("Content-Type is {header} but the body is {body}.")
+            }
+            Self::InvalidJson {
+                line,
+                column,
+                message,
+            } => format!("The JSON body is invalid at line {line}, column {column}: {message}."),
+            Self::ContentLength { typed, actual } => format!(
+                "Content-Length {typed} was typed by hand, the browser sends its own ({actual})."
+            ),
+            Self::DroppedHeader { name } => format!(
+                "{name} is written by the browser's network stack, the value typed is not sent."
+            ),
+            Self::ForbiddenMethod { method } => {
+                format!("Browsers refuse to send {method} requests, the send will fail.")
+            }
+            Self::LongRuleUrl { names } => format!(
+                "{names} can only be set by a browser rule, which may not take a URL this long. The request would be sent without them."
+            ),
+        }
+    }
+}
+
+/// What looks wrong with `request`, whose body was encoded from `state` with the type
+/// `content_type`.
+pub fn lint(request: &Request, state: &BodyState, content_type: Option<&str>) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let method = request.method.to_ascii_uppercase();
+    if forbidden::method(&method) {
+        warnings.push(Warning::ForbiddenMethod {
+            method: method.clone(),
+        });
+    }
+    if !request.body.is_empty() && ("GET" == method || "HEAD" == method) {
+        warnings.push(Warning::BodyOnGet { method });
+    }
+
+    let header = |wanted: &str| {
+        request
+            .header
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, value)| value.as_str())
+    };
+    let header_type = header("content-type").map(essence);
+    // A text body is whatever it is sent as.
+    let typed_body = Mode::Raw != state.mode || Language::Text != state.language;
+    if let (Some(header), Some(body)) = (&header_type, content_type.map(essence)) {
+        if typed_body && !same_type(header, &body) {
+            warnings.push(Warning::ContentType {
+                header: header.clone(),
+                body,
+            });
+        }
+    }
+
+    let json = Language::Json == state.language
+        || header_type
+            .as_ref()
+            .is_some_and(|header| is_suffixed(header, "json"));
+    if Mode::Raw == state.mode && json && !request.body.is_empty() {
+        if let Err(e) = serde_json::from_slice::<IgnoredAny>(&request.body) {
+            let message = e.to_string();
+            let message = message
+                .split(" at line ")
+                .next()
+                .unwrap_or(&message)
+                .to_string();
+            warnings.push(Warning::InvalidJson {
+                line: e.line(),
+                column: e.column(),
+                message,
+            });
+        }
+    }
+
+    if let Some(typed) = header("content-length") {
+        warnings.push(Warning::ContentLength {
+            typed: typed.to_string(),
+            actual: request.body.len(),
+        });
+    }
+    // Content-Length has its own warning above.
+    let mut dropped = request
+        .header
+        .keys()
+        .filter(|name| {
+            Some(Forbidden::Never) == forbidden::header(name)
+                && !name.eq_ignore_ascii_case("content-length")
+        })
+        .map(|name| Warning::DroppedHeader { name: name.clone() })
+        .collect::<Vec<_>>();
+    dropped.sort_by_key(Warning::message);
+    warnings.extend(dropped);
+
+    if request.uri.len() > forbidden::RULE_URL_LIMIT {
+        let mut ruled = request
+            .header
+            .keys()
+            .filter(|name| Some(Forbidden::Rule) == forbidden::header(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !ruled.is_empty() {
+            ruled.sort();
+            warnings.push(Warning::LongRuleUrl {
+                names: ruled.join(", "),
+            });
+        }
+    }
+    warnings
+}
+
+/// Media type of a `Content-Type` value, without its parameters.
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Whether the essences `header` and `body` are the same type, any JSON or XML type being as
+/// good as another, like `application/problem+json` for `application/json`.
+fn same_type(header: &str, body: &str) -> bool {
+    header == body
+        || ["json", "xml"]
+            .into_iter()
+            .any(|format| is_suffixed(header, format) && is_suffixed(body, format))
+}
+
+/// Whether the essence `media_type` is `*/format` or `*+format`.
+fn is_suffixed(media_type: &str, format: &str) -> bool {
+    media_type
+        .strip_suffix(format)
+        .is_some_and(|rest| rest.ends_with(['/', '+']))
+}
+
+/// Asks whether to send a request that [`lint`] found something about.
+#[component]
+pub fn LintPrompt(
+    pending: RwSignal<Option<(Request, Vec<Warning>)>>,
+    on_send: impl Fn(Request) + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    view! {
+        <Show when=move || pending.with(Option::is_some)>
+            <div class="modal modal-open">
+                <div class="modal-box flex flex-col gap-2">
+                    <h3 class="font-bold">Check the request</h3>
+                    <ul class="flex flex-col gap-1">
+                        {move || {
+                            pending
+                                .get()
+                                .map(|(_, warnings)| {
+                                    warnings
+                                        .into_iter()
+                                        .map(|warning| {
+                                            view! {
+                                                <li class="alert alert-warning py-2 text-sm">
+                                                    {warning.message()}
+                                                </li>
+                                            }
+                                        })
+                                        .collect_view()
+                                })
+                        }}
+                    </ul>
+                    <div class="modal-action">
+                        <button class="btn btn-sm" on:click=move |_| pending.set(None)>
+                            Cancel
+                        </button>
+                        <button
+                            class="btn btn-sm btn-warning"
+                            on:click=move |_| {
+                                if let Some((request, _)) = pending.get_untracked() {
+                                    pending.set(None);
+                                    on_send(request);
+                                }
+                            }
+                        >
+                            Send anyway
+                        </button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sent(method: &str, header: &[(&str, &str)], body: &str) -> Request {
+        let header = header
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Request::new(
+            method.to_string(),
+            "https://a.test".to_string(),
+            header,
+            body.into(),
+        )
+    }
+
+    fn json() -> BodyState {
+        BodyState {
+            language: Language::Json,
+            ..BodyState::default()
+        }
+    }
+
+    #[test]
+    fn clean_request() {
+        let request = sent("post", &[("Content-Type", "application/json")], "{}");
+        assert_eq!(lint(&request, &json(), Some("application/json")), []);
+    }
+
+    #[test]
+    fn body_on_get() {
+        assert_eq!(
+            lint(&sent("get", &[], "x"), &BodyState::default(), None),
+            [Warning::BodyOnGet {
+                method: "GET".to_string()
+            }]
+        );
+        assert_eq!(lint(&sent("get", &[], ""), &BodyState::default(), None), []);
+    }
+
+    #[test]
+    fn content_type_matches_by_format() {
+        let typed = |content_type| {
+            let request = sent("POST", &[("content-type", content_type)], "{}");
+            lint(&request, &json(), Some("application/json"))
+        };
+        assert_eq!(typed("application/problem+json; charset=utf-8"), []);
+        assert_eq!(typed("text/json"), []);
+        assert_eq!(
+            typed("text/plain"),
+            [Warning::ContentType {
+                header: "text/plain".to_string(),
+                body: "application/json".to_string(),
+            }]
+        );
+
+        let xml = BodyState {
+            language: Language::Xml,
+            ..BodyState::default()
+        };
+        let request = sent("PROPFIND", &[("Content-Type", "text/xml")], "<a/>");
+        assert_eq!(lint(&request, &xml, Some("application/xml")), []);
+        let request = sent("PUT", &[("Content-Type", "image/svg+xml")], "<a/>");
+        assert_eq!(lint(&request, &xml, Some("application/xml")), []);
+
+        // A text body is sent as whatever the header says.
+        let request = sent("POST", &[("Content-Type", "text/csv")], "a,b");
+        assert_eq!(
+            lint(&request, &BodyState::default(), Some("text/plain")),
+            []
+        );
+    }
+
+    #[test]
+    fn invalid_json() {
+        let request = sent("POST", &[], "{\n  \"a\": }");
+        assert_eq!(
+            lint(&request, &json(), Some("application/json")),
+            [Warning::InvalidJson {
+                line: 2,
+                column: 8,
+                message: "expected value".to_string(),
+            }]
+        );
+        // The header alone makes it JSON.
+        let request = sent("POST", &[("Content-Type", "application/vnd.api+json")], "[");
+        assert!(matches!(
+            lint(&request, &BodyState::default(), Some("text/plain"))[..],
+            [Warning::InvalidJson { .. }]
+        ));
+    }
+
+    #[test]
+    fn content_length_typed() {
+        let request = sent("POST", &[("Content-Length", "3")], "abcd");
+        assert_eq!(
+            lint(&request, &BodyState::default(), None),
+            [Warning::ContentLength {
+                typed: "3".to_string(),
+                actual: 4,
+            }]
+        );
+        let request = sent("POST", &[], "abcd");
+        assert_eq!(lint(&request, &BodyState::default(), None), []);
+    }
+
+    #[test]
+    fn forbidden_method() {
+        assert_eq!(
+            lint(&sent("trace", &[], ""), &BodyState::default(), None),
+            [Warning::ForbiddenMethod {
+                method: "TRACE".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn headers_fetch_drops() {
+        let request = sent(
+            "POST",
+            &[
+                ("Host", "b.test"),
+                ("Connection", "close"),
+                ("Cookie", "a=1"),
+            ],
+            "",
+        );
+        assert_eq!(
+            lint(&request, &BodyState::default(), None),
+            [
+                Warning::DroppedHeader {
+                    name: "Connection".to_string()
+                },
+                Warning::DroppedHeader {
+                    name: "Host".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn long_url_with_rule_headers() {
+        let long = |header: &[(&str, &str)]| {
+            let mut request = sent("GET", header, "");
+            request.uri = format!(
+                "https://a.test/?q={}",
+                "a".repeat(forbidden::RULE_URL_LIMIT)
+            );
+            lint(&request, &BodyState::default(), None)
+        };
+        assert_eq!(
+            long(&[("Referer", "https://b.test"), ("Cookie", "a=1")]),
+            [Warning::LongRuleUrl {
+                names: "Cookie, Referer".to_string()
+            }]
+        );
+        assert_eq!(long(&[("Authorization", "Bearer x")]), []);
+    }
+}
@@ -1,45 +1,334 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use http::Uri;
+use indexed_db_futures::prelude::*;
 use leptos::ev::Event;
 use leptos::prelude::*;
 use module::http::{Request, Response};
-use serde::ser::Serializer as _;
 use serde::{Deserialize, Serialize};
-use serde_wasm_bindgen::Serializer;
-use time::{ext::NumericalDuration, macros::format_description, OffsetDateTime};
+use time::{macros::format_description, OffsetDateTime};
 use tracing::error;
 use uuid::Uuid;
-use wasm_bindgen::JsValue;
-use wasm_bindgen_futures::js_sys::Object;
-use web_sys::js_sys::Map;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_extensions_sys::StorageChange;
+use web_sys::{
+    js_sys::{Array, Reflect},
+    IdbKeyRange,
+};
+
+use crate::body::BodyState;
+use crate::browser::{browser, get_local, set_local};
+use crate::compress::{truncate, Bodies};
+use crate::db::{self, from_js, open, to_js, CONTENTS, INDEXES};
+use crate::har;
+use crate::note::Annotations;
+use crate::preview::format_size;
+use crate::redact::redaction;
+use crate::retention::{retention, Retention, StorageDashboard};
+use crate::search::{Highlight, Search, SearchBar, HISTORY_SEARCH};
+use crate::vault::{
+    self, get_content, put_content, sealing_key, unlocked_key, Key, Record, VaultPanel, VaultState,
+};
+
+/// `storage.local` key of the history index before it moved to IndexedDB.
+const LEGACY_INDEXES: &str = "indexes";
+/// Unstarred history hits loaded per page.
+const PAGE_SIZE: usize = 50;
+/// `storage.local` key written after every history change, so other CDGHOPT tabs can follow.
+const HISTORY_CHANGED: &str = "history_changed";
+
+/// How long deleted history can be brought back.
+const UNDO_WINDOW: Duration = Duration::from_secs(8);
+
+/// Identifies this tab in [`HistoryChange`]s.
+static INSTANCE: LazyLock<Uuid> = LazyLock::new(Uuid::now_v7);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HistoryChange {
+    source: Uuid,
+    ids: Vec<Uuid>,
+    /// Makes every change a new value, `onChanged` skips writes of an equal value.
+    nonce: Uuid,
+}
 
-use crate::browser::{browser, get_local};
+#[derive(Debug, Clone)]
+enum Removal {
+    Ids(Vec<Uuid>),
+    Unstarred,
+}
 
-const INDEXES: &str = "indexes";
+/// Items last deleted, marked with [`LogIndexItem::deleted_at`] until the undo window closes.
+#[derive(Debug, Clone)]
+struct Deleted {
+    generation: usize,
+    ids: Vec<Uuid>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogIndexItem {
     pub id: Uuid,
     pub method: String,
     pub uri: String,
+    #[serde(default)]
+    pub host: String,
+    /// 0 if the send failed.
+    #[serde(default)]
+    pub status: u16,
+    /// Milliseconds until the response, or until the send failed.
+    #[serde(default)]
+    pub elapsed_time: i32,
+    /// Response body length in bytes.
+    #[serde(default)]
+    pub size: usize,
+    /// Approximate bytes of the stored content, see [`LogContent::stored_bytes`].
+    #[serde(default)]
+    pub bytes: usize,
+    #[serde(default)]
+    pub error: Option<String>,
     #[serde(with = "time::serde::timestamp")]
     pub done_date: OffsetDateTime,
     #[serde(default)]
     pub star: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Markdown.
+    #[serde(default)]
+    pub note: String,
+    /// A body was cut at the size cap when stored.
+    #[serde(default)]
+    pub truncated: bool,
+    /// [`Private`] fields sealed with the vault key, while the history is locked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed: Option<String>,
+    /// When it was deleted. It is hidden, and swept with its content once [`UNDO_WINDOW`] is
+    /// over.
+    #[serde(
+        default,
+        with = "time::serde::timestamp::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub deleted_at: Option<OffsetDateTime>,
+}
+
+/// The fields of a [`LogIndexItem`] telling what was requested, sealed in its record while the
+/// vault is on. The others stay in the clear for the IndexedDB indexes and pruning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Private {
+    uri: String,
+    tags: Vec<String>,
+    note: String,
+    /// Holds the URL. Left out of records sealed before it was.
+    #[serde(default)]
+    error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogContent {
     pub request: Request,
-    pub response: Response,
+    /// `None` if the send failed.
+    pub response: Option<Response>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// jq filter last applied to the response body.
+    #[serde(default)]
+    pub filter: String,
+    #[serde(default)]
+    pub bodies: Bodies,
+    /// Form fields, parts and files the request body was built from.
+    #[serde(default)]
+    pub body_state: BodyState,
+}
+
+impl LogContent {
+    /// Approximate bytes taken in storage, mostly the bodies.
+    pub fn stored_bytes(&self) -> usize {
+        let request = &self.request;
+        let request_header: usize = request
+            .header
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum();
+        let response = self.response.as_ref().map_or(0, |response| {
+            let header: usize = response
+                .header
+                .iter()
+                .map(|(key, value)| key.len() + value.len())
+                .sum();
+            header + response.body.len()
+        });
+        request.uri.len()
+            + request_header
+            + request.body.len()
+            + response
+            + self.filter.len()
+            + self.error.as_ref().map_or(0, String::len)
+            + self.body_state.file_bytes()
+    }
+}
+
+/// A history entry opened from the drawer.
+#[derive(Debug, Clone)]
+pub struct OpenedLog {
+    pub id: Uuid,
+    pub content: LogContent,
+    /// Open it in a new request tab instead of the active one.
+    pub new_tab: bool,
 }
 
 #[component]
 pub fn LogDrawer(
     indexes: RwSignal<Vec<LogIndexItem>>,
-    log_content: RwSignal<Option<LogContent>>,
+    opened: RwSignal<Option<OpenedLog>>,
 ) -> impl IntoView {
-    let load_index =
-        Action::new_local(|indexes: &RwSignal<Vec<LogIndexItem>>| load_index(*indexes));
-    load_index.dispatch(indexes);
+    let has_more = RwSignal::new(false);
+    let search = RwSignal::new(None::<Search>);
+    // Body snippets of full-text hits whose URI does not contain the text.
+    let snippets = RwSignal::new(HashMap::<Uuid, String>::new());
+
+    leptos::task::spawn_local(async move {
+        let _ = migrate()
+            .await
+            .inspect_err(|e| error!("Failed to migrate history: {e:?}"));
+        let _ = sweep()
+            .await
+            .inspect_err(|e| error!("Failed to sweep deleted history: {e:?}"));
+        let _ = prune(&retention().await)
+            .await
+            .inspect_err(|e| error!("Failed to prune history: {e:?}"));
+        let last = get_local::<Search>(HISTORY_SEARCH)
+            .await
+            .unwrap_or_default();
+        search.set(Some(last));
+    });
+
+    let load_index = Action::new_local(move |query: &Search| {
+        let query = query.clone();
+        async move {
+            let Ok((items, more, found)) = load_index(&query)
+                .await
+                .inspect_err(|e| error!("Failed to load index: {e:?}"))
+            else {
+                return;
+            };
+            // The search changed while this one was loading.
+            if Some(&query) != search.get_untracked().as_ref() {
+                return;
+            }
+            has_more.set(more);
+            snippets.set(found);
+            indexes.set(items);
+        }
+    });
+    let load_more = Action::new_local(move |query: &Search| {
+        let query = query.clone();
+        async move {
+            let last = indexes.with_untracked(|indexes| {
+                indexes
+                    .iter()
+                    .rfind(|index| !index.star)
+                    .map(|index| index.id)
+            });
+            let mut found = HashMap::new();
+            let Ok(page) = load_page(last, &query, &mut found)
+                .await
+                .inspect_err(|e| error!("Failed to load index: {e:?}"))
+            else {
+                return;
+            };
+            if Some(&query) != search.get_untracked().as_ref() {
+                return;
+            }
+            has_more.set(PAGE_SIZE == page.len());
+            snippets.update(|snippets| snippets.extend(found));
+            indexes.update(|indexes| indexes.extend(page));
+        }
+    });
+
+    Effect::new(move |_| {
+        let Some(query) = search.get() else {
+            return;
+        };
+        load_index.dispatch(query.clone());
+        leptos::task::spawn_local(async move {
+            let _ = set_local(HISTORY_SEARCH, &query)
+                .await
+                .inspect_err(|e| error!("Failed to save search: {e:?}"));
+        });
+    });
+
+    let hits = move || {
+        let query = search.get().unwrap_or_default();
+        snippets.with(|snippets| {
+            indexes
+                .get()
+                .into_iter()
+                .filter(|index| {
+                    query.matches_index(index)
+                        && (!query.needs_content(index) || snippets.contains_key(&index.id))
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    let hosts = Signal::derive(move || {
+        indexes.with(|indexes| {
+            indexes
+                .iter()
+                .map(|index| index.host.clone())
+                .filter(|host| !host.is_empty())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        })
+    });
+    let tags = Signal::derive(move || {
+        indexes.with(|indexes| {
+            indexes
+                .iter()
+                .flat_map(|index| index.tags.iter().cloned())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        })
+    });
+    let needle = Memo::new(move |_| {
+        search.with(|search| {
+            search
+                .as_ref()
+                .map(|search| search.text.clone())
+                .unwrap_or_default()
+        })
+    });
+
+    let on_changed: Closure<dyn Fn(JsValue, String)> =
+        Closure::new(move |changes: JsValue, area: String| {
+            if "local" != area {
+                return;
+            }
+            let Ok(change) = Reflect::get(&changes, &JsValue::from_str(HISTORY_CHANGED)) else {
+                return;
+            };
+            if change.is_undefined() {
+                return;
+            }
+            let change = change.unchecked_into::<StorageChange>().new_value();
+            let Ok(change) = from_js::<HistoryChange>(change) else {
+                return;
+            };
+            if *INSTANCE != change.source {
+                leptos::task::spawn_local(sync(indexes, change.ids));
+            }
+        });
+    browser()
+        .storage()
+        .on_changed()
+        .add_listener(on_changed.as_ref().unchecked_ref());
+    on_changed.forget();
+    let loading = Signal::derive(move || load_index.pending().get() || load_more.pending().get());
 
     let star = Action::new_local(|index_param: &(RwSignal<Vec<LogIndexItem>>, Uuid)| {
         let indexes = index_param.0;
@@ -47,19 +336,121 @@ pub fn LogDrawer(
         async move {
             let _ = star(indexes, id)
                 .await
-                .inspect(|e| error!("Failed to star: {e:?}"));
+                .inspect_err(|e| error!("Failed to star: {e:?}"));
         }
     });
 
-    let get_log = Action::new_local(move |id: &Uuid| {
-        let id = *id;
-        get_log(id)
+    let show_storage = RwSignal::new(false);
+    let show_vault = RwSignal::new(false);
+    let vault_state = RwSignal::new(None::<VaultState>);
+    leptos::task::spawn_local(async move {
+        vault_state.set(Some(vault::state().await));
+    });
+    let selected = RwSignal::new(HashSet::<Uuid>::new());
+    let deleted = RwSignal::new(None::<Deleted>);
+    let generation = StoredValue::new(0usize);
+    let removed = move |ids: Vec<Uuid>| {
+        if ids.is_empty() {
+            return;
+        }
+        let gone = ids.iter().collect::<HashSet<_>>();
+        indexes.update(|indexes| indexes.retain(|index| !gone.contains(&index.id)));
+        selected.update(|selected| selected.retain(|id| !gone.contains(id)));
+
+        generation.update_value(|generation| *generation += 1);
+        let current = generation.get_value();
+        deleted.set(Some(Deleted {
+            generation: current,
+            ids,
+        }));
+        set_timeout(
+            move || {
+                if deleted.with_untracked(|deleted| {
+                    deleted
+                        .as_ref()
+                        .is_some_and(|deleted| current == deleted.generation)
+                }) {
+                    deleted.set(None);
+                }
+                leptos::task::spawn_local(async move {
+                    let _ = sweep()
+                        .await
+                        .inspect_err(|e| error!("Failed to sweep deleted history: {e:?}"));
+                });
+            },
+            UNDO_WINDOW,
+        );
+    };
+    let delete = Action::new_local(move |removal: &Removal| {
+        let removal = removal.clone();
+        async move {
+            let ids = match removal {
+                Removal::Ids(ids) => delete(ids).await,
+                Removal::Unstarred => delete_unstarred().await,
+            };
+            if let Ok(ids) = ids.inspect_err(|e| error!("Failed to delete: {e:?}")) {
+                removed(ids);
+            }
+        }
+    });
+    let undo = Action::new_local(move |_: &()| {
+        let ids = deleted
+            .try_update(Option::take)
+            .flatten()
+            .map(|deleted| deleted.ids)
+            .unwrap_or_default();
+        async move {
+            let _ = restore(ids)
+                .await
+                .inspect_err(|e| error!("Failed to undo delete: {e:?}"));
+            // The restored items take their place in the current search and page.
+            if let Some(query) = search.get_untracked() {
+                load_index.dispatch(query);
+            }
+        }
+    });
+
+    let annotate = Action::new_local(move |(id, tags, note): &(Uuid, Vec<String>, String)| {
+        let (id, tags, note) = (*id, tags.clone(), note.clone());
+        async move {
+            let _ = annotate(indexes, id, tags, note)
+                .await
+                .inspect_err(|e| error!("Failed to annotate: {e:?}"));
+        }
+    });
+    let export = Action::new_local(move |_: &()| {
+        let items = selected.with_untracked(|selected| {
+            hits()
+                .into_iter()
+                .filter(|index| selected.is_empty() || selected.contains(&index.id))
+                .collect()
+        });
+        async move {
+            let _ = har::export(items)
+                .await
+                .inspect_err(|e| error!("Failed to export HAR: {e:?}"));
+        }
+    });
+    let filter_tag = move |tag: String| {
+        search.update(|search| {
+            if let Some(search) = search {
+                search.tag = tag;
+            }
+        })
+    };
+
+    let get_log = Action::new_local(move |&(id, new_tab): &(Uuid, bool)| async move {
+        get_log(id).await.map(|content| OpenedLog {
+            id,
+            content,
+            new_tab,
+        })
     });
 
     let log = get_log.value();
     Effect::new(move |_| {
         if let Some(Some(log)) = log.get() {
-            log_content.set(Some(log));
+            opened.set(Some(log));
         }
     });
 
@@ -73,51 +464,274 @@ pub fn LogDrawer(
             </div>
             <div class="drawer-side z-50">
                 <label for="log-drawer" aria-label="close sidebar" class="drawer-overlay"></label>
-                <ul class="menu bg-base-200 text-base-content min-h-full w-1/2">
-                    <For
-                        each=move || indexes.get().into_iter()
-                        key=|index| index.id
-                        children=move |index| {
-                            view! {
-                                <li>
-                                    <div class="card bg-base-100 shadow-xl w-full">
-                                        <div class="card-body w-full py-0">
-                                            <h2 class="card-title">
-                                                {index.method}
-                                                <div class="badge badge-xs">
+                <div class="bg-base-200 text-base-content min-h-full w-1/2">
+                    <SearchBar search=search hosts=hosts tags=tags />
+                    <div class="flex justify-end gap-2 px-2">
+                        <Show when=move || selected.with(|selected| !selected.is_empty())>
+                            <button
+                                class="btn btn-sm btn-error"
+                                on:click=move |_| {
+                                    let ids = selected.get_untracked().into_iter().collect();
+                                    delete.dispatch(Removal::Ids(ids));
+                                }
+                            >
+                                {move || format!("Delete selected ({})", selected.with(HashSet::len))}
+                            </button>
+                            <button
+                                class="btn btn-sm btn-ghost"
+                                on:click=move |_| selected.update(HashSet::clear)
+                            >
+                                Deselect
+                            </button>
+                        </Show>
+                        <button
+                            class="btn btn-sm btn-ghost"
+                            on:click=move |_| {
+                                delete.dispatch(Removal::Unstarred);
+                            }
+                        >
+                            Clear all unstarred
+                        </button>
+                        <button
+                            class="btn btn-sm btn-ghost"
+                            title="Selected entries, or all shown"
+                            disabled=move || export.pending().get()
+                            on:click=move |_| {
+                                export.dispatch(());
+                            }
+                        >
+                            Export HAR
+                        </button>
+                        <button
+                            class="btn btn-sm btn-ghost"
+                            class:btn-active=show_vault
+                            on:click=move |_| show_vault.update(|show| *show = !*show)
+                        >
+                            {move || match vault_state.get() {
+                                Some(VaultState::Locked) => "Locked",
+                                Some(VaultState::Unlocked) => "Encrypted",
+                                _ => "Encryption",
+                            }}
+                        </button>
+                        <button
+                            class="btn btn-sm btn-ghost"
+                            class:btn-active=show_storage
+                            on:click=move |_| show_storage.update(|show| *show = !*show)
+                        >
+                            Storage
+                        </button>
+                    </div>
+                    <Show when=move || {
+                        show_vault.get() || Some(VaultState::Locked) == vault_state.get()
+                    }>
+                        <VaultPanel state=vault_state on_change=move || search.update(|_| {}) />
+                    </Show>
+                    <Show when=move || show_storage.get()>
+                        <StorageDashboard on_pruned=move || search.update(|_| {}) />
+                    </Show>
+                    <ul class="menu w-full">
+                        <For
+                            each=hits
+                            key=|index| {
+                                (
+                                    index.id,
+                                    index.star,
+                                    index.sealed.is_some(),
+                                    index.tags.clone(),
+                                    index.note.clone(),
+                                )
+                            }
+                            children=move |index| {
+                                let id = index.id;
+                                let uri = match index.sealed {
+                                    Some(_) => "Unlock the history to see the URL".to_string(),
+                                    None => index.uri.clone(),
+                                };
+                                let border = outcome_border(&index);
+                                let status = match &index.error {
+                                    Some(_) => "error".to_string(),
+                                    None => index.status.to_string(),
+                                };
+                                view! {
+                                    <li>
+                                        <div class=format!(
+                                            "card bg-base-100 shadow-xl w-full border-l-4 {border}",
+                                        )>
+                                            <div class="card-body w-full py-0">
+                                                <h2 class="card-title">
+                                                    {index.method}
+                                                    <div class="badge badge-xs">
+                                                        {index
+                                                            .done_date
+                                                            .format(
+                                                                format_description!(
+                                                                    "[year]-[month]-[day] [hour]:[minute]:[second]"
+                                                                ),
+                                                            )}
+                                                    </div>
+                                                </h2>
+                                                <div class="flex gap-1">
+                                                    <div class="badge badge-sm badge-outline">
+                                                        {status}
+                                                    </div>
+                                                    <div class="badge badge-sm badge-ghost">
+                                                        {format!("{} ms", index.elapsed_time)}
+                                                    </div>
+                                                    {index
+                                                        .error
+                                                        .is_none()
+                                                        .then(|| {
+                                                            view! {
+                                                                <div class="badge badge-sm badge-ghost">
+                                                                    {format_size(index.size)}
+                                                                </div>
+                                                            }
+                                                        })}
                                                     {index
-                                                        .done_date
-                                                        .format(
-                                                            format_description!(
-                                                                "[year]-[month]-[day] [hour]:[minute]:[second]"
-                                                            ),
-                                                        )}
+                                                        .truncated
+                                                        .then(|| {
+                                                            view! {
+                                                                <div
+                                                                    class="badge badge-sm badge-warning"
+                                                                    title="A body above the size cap was cut short in history"
+                                                                >
+                                                                    truncated
+                                                                </div>
+                                                            }
+                                                        })}
+                                                </div>
+                                                <p class="break-all">
+                                                    {move || {
+                                                        view! {
+                                                            <Highlight text=uri.clone() needle=needle.get() />
+                                                        }
+                                                    }}
+                                                </p>
+                                                {index
+                                                    .error
+                                                    .clone()
+                                                    .filter(|error| !error.is_empty())
+                                                    .map(|error| {
+                                                        view! {
+                                                            <p class="text-xs text-error break-all">{error}</p>
+                                                        }
+                                                    })}
+                                                {move || {
+                                                    snippets
+                                                        .with(|snippets| snippets.get(&id).cloned())
+                                                        .map(|snippet| {
+                                                            view! {
+                                                                <p class="text-xs opacity-70 break-all">
+                                                                    "…"
+                                                                    <Highlight text=snippet needle=needle.get() />
+                                                                    "…"
+                                                                </p>
+                                                            }
+                                                        })
+                                                }}
+                                                <Annotations
+                                                    tags=index.tags.clone()
+                                                    note=index.note.clone()
+                                                    on_save=move |tags, note| {
+                                                        annotate.dispatch((id, tags, note));
+                                                    }
+                                                    on_tag=filter_tag
+                                                />
+                                            </div>
+                                            <div class="card-actions justify-end items-center">
+                                                <input
+                                                    type="checkbox"
+                                                    class="checkbox checkbox-sm"
+                                                    prop:checked=move || {
+                                                        selected.with(|selected| selected.contains(&id))
+                                                    }
+                                                    on:change=move |ev| {
+                                                        let checked = event_target_checked(&ev);
+                                                        selected
+                                                            .update(|selected| {
+                                                                if checked {
+                                                                    selected.insert(id);
+                                                                } else {
+                                                                    selected.remove(&id);
+                                                                }
+                                                            });
+                                                    }
+                                                />
+                                                <StarButton
+                                                    checked=index.star
+                                                    on_change=move |_| {
+                                                        star.dispatch((indexes, index.id));
+                                                    }
+                                                />
+                                                <div
+                                                    class="badge badge-primary"
+                                                    on:click=move |_| {
+                                                        get_log.dispatch((index.id, false));
+                                                    }
+                                                >
+                                                    Open
+                                                </div>
+                                                <div
+                                                    class="badge badge-primary badge-outline"
+                                                    on:click=move |_| {
+                                                        get_log.dispatch((index.id, true));
+                                                    }
+                                                >
+                                                    New tab
+                                                </div>
+                                                <div
+                                                    class="badge badge-error badge-outline"
+                                                    on:click=move |_| {
+                                                        delete.dispatch(Removal::Ids(vec![id]));
+                                                    }
+                                                >
+                                                    Delete
                                                 </div>
-                                            </h2>
-                                            <p class="break-all">{index.uri}</p>
-                                        </div>
-                                        <div class="card-actions justify-end">
-                                            <StarButton
-                                                checked=index.star
-                                                on_change=move |_| {
-                                                    star.dispatch((indexes, index.id));
-                                                }
-                                            />
-                                            <div
-                                                class="badge badge-primary"
-                                                on:click=move |_| {
-                                                    get_log.dispatch(index.id);
-                                                }
-                                            >
-                                                Open
                                             </div>
                                         </div>
-                                    </div>
-                                </li>
+                                    </li>
+                                }
                             }
-                        }
-                    />
-                </ul>
+                        />
+                        <Show when=move || has_more.get()>
+                            <li>
+                                <button
+                                    class="btn btn-ghost btn-sm"
+                                    disabled=loading
+                                    on:click=move |_| {
+                                        load_more
+                                            .dispatch(search.get_untracked().unwrap_or_default());
+                                    }
+                                >
+                                    Load more
+                                </button>
+                            </li>
+                        </Show>
+                    </ul>
+                </div>
+                <Show when=move || deleted.with(Option::is_some)>
+                    <div class="toast toast-end">
+                        <div class="alert">
+                            <span>
+                                {move || {
+                                    let count = deleted
+                                        .with(|deleted| {
+                                            deleted.as_ref().map_or(0, |deleted| deleted.ids.len())
+                                        });
+                                    format!("Deleted {count} entries")
+                                }}
+                            </span>
+                            <button
+                                class="btn btn-sm btn-primary"
+                                on:click=move |_| {
+                                    undo.dispatch(());
+                                }
+                            >
+                                Undo
+                            </button>
+                        </div>
+                    </div>
+                </Show>
             </div>
         </div>
     }
@@ -161,113 +775,596 @@ pub fn StarButton(checked: bool, on_change: impl FnMut(Event) + 'static) -> impl
     }
 }
 
-async fn load_index(indexes: RwSignal<Vec<LogIndexItem>>) {
-    let mut index_items: Vec<LogIndexItem> = get_local(INDEXES)
-        .await
-        .inspect_err(|e| error!("Failed to load index: {e:?}"))
-        .unwrap_or_default();
-
-    let ninety_day_before = OffsetDateTime::now_local().map(|now| now.checked_sub(90.days()));
-    let mut i = index_items.len();
-    if let Ok(Some(ninety_day_before)) = ninety_day_before {
-        for (ii, item) in index_items.iter().enumerate() {
-            if !item.star && item.done_date < ninety_day_before {
-                i = ii;
+/// Starred hits followed by the first page of the other hits, whether there are more and the
+/// body snippets of full-text hits.
+async fn load_index(
+    search: &Search,
+) -> Result<(Vec<LogIndexItem>, bool, HashMap<Uuid, String>), JsValue> {
+    let mut snippets = HashMap::new();
+    let mut items = starred(search, &mut snippets).await?;
+    if search.starred {
+        return Ok((items, false, snippets));
+    }
+    let page = load_page(None, search, &mut snippets).await?;
+    let has_more = PAGE_SIZE == page.len();
+    items.extend(page);
+    Ok((items, has_more, snippets))
+}
+
+async fn starred(
+    search: &Search,
+    snippets: &mut HashMap<Uuid, String>,
+) -> Result<Vec<LogIndexItem>, JsValue> {
+    let key = unlocked_key().await;
+    let db = open().await?;
+    let tx = db.transaction_on_multi(&[INDEXES, CONTENTS])?;
+    let all = tx
+        .object_store(INDEXES)?
+        .index("starred")?
+        .get_all()?
+        .await?;
+    let contents = tx.object_store(CONTENTS)?;
+    let mut items = Vec::new();
+    for item in all.iter() {
+        let item = open_index(from_js(item)?, key.as_ref())?;
+        if item.deleted_at.is_none()
+            && is_hit(search, &item, &contents, key.as_ref(), snippets).await?
+        {
+            items.push(item);
+        }
+    }
+    items.sort_by_key(|item| Reverse(item.id));
+    Ok(items)
+}
+
+/// Unstarred hits older than `after`, newest first. Ids are v7 uuids, so key order is date
+/// order.
+async fn load_page(
+    after: Option<Uuid>,
+    search: &Search,
+    snippets: &mut HashMap<Uuid, String>,
+) -> Result<Vec<LogIndexItem>, JsValue> {
+    let key = unlocked_key().await;
+    let db = open().await?;
+    let tx = db.transaction_on_multi(&[INDEXES, CONTENTS])?;
+    let store = tx.object_store(INDEXES)?;
+    let contents = tx.object_store(CONTENTS)?;
+    let cursor = match after {
+        Some(id) => {
+            let range =
+                IdbKeyRange::upper_bound_with_open(&JsValue::from_str(&id.to_string()), true)?;
+            store
+                .open_cursor_with_range_and_direction_owned(range, IdbCursorDirection::Prev)?
+                .await?
+        }
+        None => {
+            store
+                .open_cursor_with_direction(IdbCursorDirection::Prev)?
+                .await?
+        }
+    };
+
+    let mut items = Vec::new();
+    if let Some(cursor) = cursor {
+        loop {
+            let item = open_index(from_js(cursor.value())?, key.as_ref())?;
+            if !item.star
+                && item.deleted_at.is_none()
+                && is_hit(search, &item, &contents, key.as_ref(), snippets).await?
+            {
+                items.push(item);
+                if PAGE_SIZE == items.len() {
+                    break;
+                }
+            }
+            if !cursor.continue_cursor()?.await? {
                 break;
             }
         }
     }
-    let delete_items = index_items.split_off(i);
+    Ok(items)
+}
+
+/// Whether `item` is a hit for `search`, reading its bodies from `contents` in the same
+/// transaction when the URI alone does not tell. Sealed bodies are only searched with `key`.
+async fn is_hit(
+    search: &Search,
+    item: &LogIndexItem,
+    contents: &IdbObjectStore<'_>,
+    key: Option<&Key>,
+    snippets: &mut HashMap<Uuid, String>,
+) -> Result<bool, JsValue> {
+    if !search.matches_index(item) {
+        return Ok(false);
+    }
+    if !search.needs_content(item) {
+        return Ok(true);
+    }
+    let Some(content) = contents.get_owned(item.id.to_string())?.await? else {
+        return Ok(false);
+    };
+    let Ok(content) = Record::from_js(content)?.open(key) else {
+        return Ok(false);
+    };
+    match search.snippet(&content) {
+        Some(snippet) => {
+            snippets.insert(item.id, snippet);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
 
-    indexes.set(index_items);
+/// Deletes the unstarred items `retention` does not keep: those past its age, and, newest
+/// first, those beyond its entry count or content bytes. Items indexed before their size was
+/// recorded get it on the way.
+pub async fn prune(retention: &Retention) -> Result<(), JsValue> {
+    let cutoff = retention.cutoff();
+    let max_count = retention.max_count();
+    let max_bytes = retention.max_bytes();
 
-    for item in delete_items {
-        let key = JsValue::from_str(&item.id.to_string());
-        let _ = browser()
-            .storage()
-            .local()
-            .remove(&key)
-            .await
-            .inspect_err(|e| error!("Failed to delete: {e:?}"));
+    let db = open().await?;
+    let tx =
+        db.transaction_on_multi_with_mode(&[INDEXES, CONTENTS], IdbTransactionMode::Readwrite)?;
+    let contents = tx.object_store(CONTENTS)?;
+    let mut ids = Vec::new();
+    let (mut count, mut bytes, mut full) = (0, 0, false);
+    if let Some(cursor) = tx
+        .object_store(INDEXES)?
+        .open_cursor_with_direction(IdbCursorDirection::Prev)?
+        .await?
+    {
+        loop {
+            let mut item: LogIndexItem = from_js(cursor.value())?;
+            let key = item.id.to_string();
+            if 0 == item.bytes {
+                if let Some(content) = contents.get_owned(key.as_str())?.await? {
+                    item.bytes = Record::from_js(content)?.stored_bytes();
+                    // Written back as read, sealed or not.
+                    cursor.update(&index_record(&item, None)?)?.await?;
+                }
+            }
+
+            if !item.star && item.deleted_at.is_none() {
+                full = full
+                    || max_count.is_some_and(|max| count >= max)
+                    || max_bytes.is_some_and(|max| bytes + item.bytes > max);
+                let expired = cutoff.is_some_and(|cutoff| item.done_date < cutoff);
+                if full || expired {
+                    cursor.delete()?;
+                    contents.delete_owned(key.as_str())?;
+                    ids.push(item.id);
+                } else {
+                    count += 1;
+                    bytes += item.bytes;
+                }
+            }
+            if !cursor.continue_cursor()?.await? {
+                break;
+            }
+        }
+    }
+    tx.await.into_result()?;
+
+    if !ids.is_empty() {
+        notify(ids).await;
     }
+    Ok(())
+}
+
+/// Moves history kept in `storage.local` by older versions into IndexedDB, once.
+async fn migrate() -> Result<(), JsValue> {
+    let Ok(index_items) = get_local::<Vec<LogIndexItem>>(LEGACY_INDEXES).await else {
+        return Ok(());
+    };
+
+    let mut contents = Vec::with_capacity(index_items.len());
+    for item in index_items.iter() {
+        let content = get_local::<LogContent>(&item.id.to_string()).await.ok();
+        contents.push(content);
+    }
+
+    let vault_key = sealing_key().await?;
+    let db = open().await?;
+    let tx =
+        db.transaction_on_multi_with_mode(&[INDEXES, CONTENTS], IdbTransactionMode::Readwrite)?;
+    let index_store = tx.object_store(INDEXES)?;
+    let content_store = tx.object_store(CONTENTS)?;
+    let keys = Array::of1(&JsValue::from_str(LEGACY_INDEXES));
+    for (mut item, content) in index_items.into_iter().zip(contents) {
+        let key = item.id.to_string();
+        item.host = host(&item.uri);
+        if let Some(content) = content {
+            if let Some(response) = &content.response {
+                item.status = response.status;
+                item.elapsed_time = response.elapsed_time;
+                item.size = response.body.len();
+            }
+            let record = Record::new(content, vault_key.as_ref())?;
+            item.bytes = record.stored_bytes();
+            content_store.put_key_val_owned(key.as_str(), &record.to_js()?)?;
+        }
+        index_store.put_key_val_owned(key.as_str(), &index_record(&item, vault_key.as_ref())?)?;
+        keys.push(&JsValue::from_str(&key));
+    }
+    tx.await.into_result()?;
+
+    browser().storage().local().remove(&keys).await?;
+    Ok(())
 }
 
 async fn get_log(id: Uuid) -> Option<LogContent> {
-    get_local(&id.to_string())
+    get_content(&id.to_string())
         .await
         .inspect_err(|e| error!("{e:?}"))
         .ok()
+        .flatten()
 }
 
 pub async fn save_log(
     indexes: RwSignal<Vec<LogIndexItem>>,
     request: Request,
+    body_state: BodyState,
     response: Response,
-) -> Result<(), JsValue> {
-    let id = Uuid::now_v7();
+    filter: String,
+) -> Result<Option<Uuid>, JsValue> {
     let index = LogIndexItem {
-        id,
+        id: Uuid::now_v7(),
         method: request.method.clone(),
+        host: host(&request.uri),
         uri: request.uri.clone(),
+        status: response.status,
+        elapsed_time: response.elapsed_time,
+        size: response.body.len(),
+        bytes: 0,
+        error: None,
         done_date: response.done_date,
         star: false,
+        tags: Vec::new(),
+        note: String::new(),
+        truncated: false,
+        sealed: None,
+        deleted_at: None,
+    };
+    let content = LogContent {
+        request,
+        response: Some(response),
+        error: None,
+        filter,
+        bodies: Bodies::default(),
+        body_state,
+    };
+    save(indexes, index, content).await
+}
+
+/// Logs a send that failed after `elapsed_time` milliseconds, so it can be reopened and retried.
+pub async fn save_error(
+    indexes: RwSignal<Vec<LogIndexItem>>,
+    request: Request,
+    body_state: BodyState,
+    error: String,
+    elapsed_time: i32,
+) -> Result<Option<Uuid>, JsValue> {
+    let index = LogIndexItem {
+        id: Uuid::now_v7(),
+        method: request.method.clone(),
+        host: host(&request.uri),
+        uri: request.uri.clone(),
+        status: 0,
+        elapsed_time,
+        size: 0,
+        bytes: 0,
+        error: Some(error.clone()),
+        // When the send started, like the date of a response.
+        done_date: OffsetDateTime::now_utc() - time::Duration::milliseconds(elapsed_time.into()),
+        star: false,
+        tags: Vec::new(),
+        note: String::new(),
+        truncated: false,
+        sealed: None,
+        deleted_at: None,
+    };
+    let content = LogContent {
+        request,
+        response: None,
+        error: Some(error),
+        filter: String::new(),
+        bodies: Bodies::default(),
+        body_state,
+    };
+    save(indexes, index, content).await
+}
+
+/// Stores `index` and `content`, unless the retention policy never stores the host.
+async fn save(
+    indexes: RwSignal<Vec<LogIndexItem>>,
+    mut index: LogIndexItem,
+    mut content: LogContent,
+) -> Result<Option<Uuid>, JsValue> {
+    let retention = retention().await;
+    if retention.never_stores(&index.host) {
+        return Ok(None);
+    }
+    redaction().await.apply(&mut content);
+    index.uri = content.request.uri.clone();
+    index.error.clone_from(&content.error);
+    truncate(&mut content, retention.max_body_bytes());
+    index.truncated = content.bodies.truncated();
+    let vault_key = sealing_key().await?;
+    let record = Record::new(content, vault_key.as_ref())?;
+    index.bytes = record.stored_bytes();
+    let id = index.id;
+    let db = open().await?;
+    let tx =
+        db.transaction_on_multi_with_mode(&[INDEXES, CONTENTS], IdbTransactionMode::Readwrite)?;
+    let key = id.to_string();
+    tx.object_store(INDEXES)?
+        .put_key_val_owned(key.as_str(), &index_record(&index, vault_key.as_ref())?)?;
+    tx.object_store(CONTENTS)?
+        .put_key_val_owned(key.as_str(), &record.to_js()?)?;
+    tx.await.into_result()?;
+
+    indexes.update(|indexes| place(indexes, index));
+    notify(vec![id]).await;
+
+    Ok(Some(id))
+}
+
+pub async fn save_filter(id: Uuid, filter: String) -> Result<(), JsValue> {
+    let key = id.to_string();
+    let Some(mut content) = get_content(&key).await? else {
+        return Ok(());
+    };
+    if content.filter == filter {
+        return Ok(());
+    }
+    content.filter = filter;
+    put_content(&key, content).await
+}
+
+pub async fn star(indexes: RwSignal<Vec<LogIndexItem>>, id: Uuid) -> Result<(), JsValue> {
+    let key = id.to_string();
+    // Starring works while locked, a sealed item is written back sealed.
+    let vault_key = unlocked_key().await;
+    let db = open().await?;
+    let tx = db.transaction_on_one_with_mode(INDEXES, IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(INDEXES)?;
+    let Some(item) = store.get_owned(key.as_str())?.await? else {
+        return Ok(());
+    };
+    let mut item = open_index(from_js(item)?, vault_key.as_ref())?;
+    item.star = !item.star;
+    store.put_key_val_owned(key.as_str(), &index_record(&item, vault_key.as_ref())?)?;
+    tx.await.into_result()?;
+
+    indexes.update(|indexes| place(indexes, item));
+    notify(vec![id]).await;
+
+    Ok(())
+}
+
+/// Sets the tags and note of the item `id`.
+pub async fn annotate(
+    indexes: RwSignal<Vec<LogIndexItem>>,
+    id: Uuid,
+    tags: Vec<String>,
+    note: String,
+) -> Result<(), JsValue> {
+    let key = id.to_string();
+    let vault_key = sealing_key().await?;
+    let db = open().await?;
+    let tx = db.transaction_on_one_with_mode(INDEXES, IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(INDEXES)?;
+    let Some(item) = store.get_owned(key.as_str())?.await? else {
+        return Ok(());
     };
-    let content = LogContent { request, response };
-    indexes.update(|indexes| {
-        let mut i = indexes.len();
-        for (ii, indexed) in indexes.iter().enumerate() {
-            if !indexed.star {
-                i = ii;
+    let mut item = open_index(from_js(item)?, vault_key.as_ref())?;
+    item.tags = tags;
+    item.note = note;
+    store.put_key_val_owned(key.as_str(), &index_record(&item, vault_key.as_ref())?)?;
+    tx.await.into_result()?;
+
+    indexes.update(|indexes| place(indexes, item));
+    notify(vec![id]).await;
+
+    Ok(())
+}
+
+/// Marks the items `ids` deleted, returning those found for [`restore`].
+async fn delete(ids: Vec<Uuid>) -> Result<Vec<Uuid>, JsValue> {
+    let deleted_at = OffsetDateTime::now_utc();
+    let db = open().await?;
+    let tx = db.transaction_on_one_with_mode(INDEXES, IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(INDEXES)?;
+    let mut found = Vec::with_capacity(ids.len());
+    for id in ids {
+        let key = id.to_string();
+        let Some(item) = store.get_owned(key.as_str())?.await? else {
+            continue;
+        };
+        let mut item: LogIndexItem = from_js(item)?;
+        item.deleted_at = Some(deleted_at);
+        // Written back as read, sealed or not.
+        store.put_key_val_owned(key.as_str(), &index_record(&item, None)?)?;
+        found.push(id);
+    }
+    tx.await.into_result()?;
+
+    notify(found.clone()).await;
+    Ok(found)
+}
+
+/// Marks every unstarred item deleted, returning them for [`restore`].
+async fn delete_unstarred() -> Result<Vec<Uuid>, JsValue> {
+    let deleted_at = OffsetDateTime::now_utc();
+    let db = open().await?;
+    let tx = db.transaction_on_one_with_mode(INDEXES, IdbTransactionMode::Readwrite)?;
+    let mut ids = Vec::new();
+    if let Some(cursor) = tx.object_store(INDEXES)?.open_cursor()?.await? {
+        loop {
+            let mut item: LogIndexItem = from_js(cursor.value())?;
+            if !item.star && item.deleted_at.is_none() {
+                item.deleted_at = Some(deleted_at);
+                cursor.update(&index_record(&item, None)?)?.await?;
+                ids.push(item.id);
+            }
+            if !cursor.continue_cursor()?.await? {
                 break;
             }
         }
-        indexes.insert(i, index);
-    });
+    }
+    tx.await.into_result()?;
 
-    let serializer = Serializer::json_compatible();
-    let index_key = JsValue::from_str(INDEXES);
-    let index_value = serializer.serialize_some(&indexes.get())?;
-    let content_key = JsValue::from_str(&id.to_string());
-    let content_value = serializer.serialize_some(&content)?;
-    let items = Map::new()
-        .set(&index_key, &index_value)
-        .set(&content_key, &content_value);
-    let items = Object::from_entries(&items);
-    if let Ok(ref items) = items {
-        browser().storage().local().set(items).await?;
+    notify(ids.clone()).await;
+    Ok(ids)
+}
+
+/// Brings back the deleted items `ids` not swept yet.
+async fn restore(ids: Vec<Uuid>) -> Result<(), JsValue> {
+    if ids.is_empty() {
+        return Ok(());
     }
+    let db = open().await?;
+    let tx = db.transaction_on_one_with_mode(INDEXES, IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(INDEXES)?;
+    for id in ids.iter() {
+        let key = id.to_string();
+        let Some(item) = store.get_owned(key.as_str())?.await? else {
+            continue;
+        };
+        let mut item: LogIndexItem = from_js(item)?;
+        item.deleted_at = None;
+        store.put_key_val_owned(key.as_str(), &index_record(&item, None)?)?;
+    }
+    tx.await.into_result()?;
 
+    notify(ids).await;
     Ok(())
 }
 
-pub async fn star(indexes: RwSignal<Vec<LogIndexItem>>, id: Uuid) -> Result<(), JsValue> {
-    indexes.update(|indexes| {
-        let i = indexes
-            .iter()
-            .enumerate()
-            .filter_map(|(i, index)| if id == index.id { Some(i) } else { None })
-            .next();
-
-        if let Some(i) = i {
-            let mut index = indexes.remove(i);
-            if index.star {
-                index.star = false;
-                indexes.push(index);
-            } else {
-                index.star = true;
-                indexes.insert(0, index);
+/// Removes the items deleted longer than [`UNDO_WINDOW`] ago, with their contents.
+async fn sweep() -> Result<(), JsValue> {
+    let cutoff = OffsetDateTime::now_utc() - UNDO_WINDOW;
+    let db = open().await?;
+    let tx =
+        db.transaction_on_multi_with_mode(&[INDEXES, CONTENTS], IdbTransactionMode::Readwrite)?;
+    let contents = tx.object_store(CONTENTS)?;
+    if let Some(cursor) = tx.object_store(INDEXES)?.open_cursor()?.await? {
+        loop {
+            let item: LogIndexItem = from_js(cursor.value())?;
+            if item.deleted_at.is_some_and(|at| at <= cutoff) {
+                cursor.delete()?;
+                contents.delete_owned(item.id.to_string().as_str())?;
+            }
+            if !cursor.continue_cursor()?.await? {
+                break;
             }
         }
-    });
+    }
+    tx.await.into_result()?;
+    Ok(())
+}
+
+/// Puts `item` in its place in the drawer list: starred first, then newest first.
+fn place(indexes: &mut Vec<LogIndexItem>, item: LogIndexItem) {
+    indexes.retain(|index| item.id != index.id);
+    let i = indexes
+        .iter()
+        .position(|index| (index.star, index.id) < (item.star, item.id))
+        .unwrap_or(indexes.len());
+    indexes.insert(i, item);
+}
+
+/// Tells other CDGHOPT tabs that the index items `ids` changed.
+async fn notify(ids: Vec<Uuid>) {
+    let change = HistoryChange {
+        source: *INSTANCE,
+        ids,
+        nonce: Uuid::now_v7(),
+    };
+    let _ = set_local(HISTORY_CHANGED, &change)
+        .await
+        .inspect_err(|e| error!("Failed to notify history change: {e:?}"));
+}
 
-    let serializer = Serializer::json_compatible();
-    let index_key = JsValue::from_str(INDEXES);
-    let index_value = serializer.serialize_some(&indexes.get())?;
-    let items = Map::new().set(&index_key, &index_value);
-    let items = Object::from_entries(&items);
-    if let Ok(ref items) = items {
-        browser().storage().local().set(items).await?;
+/// Reloads the index items `ids` changed by another tab.
+async fn sync(indexes: RwSignal<Vec<LogIndexItem>>, ids: Vec<Uuid>) {
+    let vault_key = unlocked_key().await;
+    for id in ids {
+        let item = db::get::<LogIndexItem>(INDEXES, &id.to_string())
+            .await
+            .and_then(|item| {
+                item.map(|item| open_index(item, vault_key.as_ref()))
+                    .transpose()
+            })
+            .inspect_err(|e| error!("Failed to sync history: {e:?}"));
+        match item {
+            Ok(Some(item)) if item.deleted_at.is_none() => {
+                indexes.update(|indexes| place(indexes, item))
+            }
+            Ok(_) => indexes.update(|indexes| indexes.retain(|index| id != index.id)),
+            Err(_) => {}
+        }
     }
+}
 
-    Ok(())
+/// Border colour of a drawer card by how the request went.
+fn outcome_border(item: &LogIndexItem) -> &'static str {
+    if item.error.is_some() {
+        return "border-error";
+    }
+    match item.status {
+        200..=299 => "border-success",
+        300..=399 => "border-info",
+        400..=499 => "border-warning",
+        500..=599 => "border-error",
+        _ => "border-transparent",
+    }
+}
+
+/// Index record as stored, with a `starred` field only on starred items since IndexedDB can
+/// not index booleans. The [`Private`] fields are sealed with `key`, those of an item read
+/// sealed stay as they were.
+pub fn index_record(item: &LogIndexItem, key: Option<&Key>) -> Result<JsValue, JsValue> {
+    let mut item = item.clone();
+    if let (None, Some(key)) = (&item.sealed, key) {
+        let private = Private {
+            uri: std::mem::take(&mut item.uri),
+            tags: std::mem::take(&mut item.tags),
+            note: std::mem::take(&mut item.note),
+            // Failed sends stay marked, without the message.
+            error: item.error.as_mut().map(std::mem::take),
+        };
+        item.sealed = Some(key.seal_value(&private)?);
+    }
+    let record = to_js(&item)?;
+    if item.star {
+        Reflect::set(
+            &record,
+            &JsValue::from_str("starred"),
+            &JsValue::from_f64(1.0),
+        )?;
+    }
+    Ok(record)
+}
+
+/// `item` as read from its record, with its [`Private`] fields opened with `key`. Without a key
+/// they stay sealed.
+pub fn open_index(mut item: LogIndexItem, key: Option<&Key>) -> Result<LogIndexItem, JsValue> {
+    if let (Some(sealed), Some(key)) = (&item.sealed, key) {
+        let private: Private = key.open_value(sealed)?;
+        item.uri = private.uri;
+        item.tags = private.tags;
+        item.note = private.note;
+        item.error = private.error.or(item.error);
+        item.sealed = None;
+    }
+    Ok(item)
+}
+
+fn host(uri: &str) -> String {
+    uri.parse::<Uri>()
+        .ok()
+        .and_then(|uri| uri.host().map(|host| host.to_string()))
+        .unwrap_or_default()
 }
@@ -9,12 +9,32 @@ use tracing_web::MakeWebConsoleWriter;
 mod app;
 mod body;
 mod browser;
+mod compress;
+mod db;
+mod draft;
+mod editor;
+mod filter;
+mod har;
 mod header;
+mod hex;
+mod json;
+mod lint;
 mod log;
 mod method;
+mod note;
+mod params;
+mod preview;
+mod redact;
 mod response;
+mod retention;
+mod sandbox;
+mod search;
 mod send;
+mod syntax;
+mod tab;
+mod transfer;
 mod uri;
+mod vault;
 
 fn main() {
     console_error_panic_hook::set_once();
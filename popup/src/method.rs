@@ -1,29 +1,352 @@
+use std::collections::BTreeSet;
+
 use leptos::prelude::*;
+use module::forbidden;
 use thaw_utils::class_list;
+use uuid::Uuid;
+
+use crate::body::{BodyState, Language, Mode};
+use crate::log::LogIndexItem;
+
+/// Methods offered by [`MethodSelect`].
+pub const METHODS: [&str; 9] = [
+    "CONNECT", "DELETE", "GET", "HEAD", "OPTIONS", "PATCH", "POST", "PUT", "TRACE",
+];
+
+/// A WebDAV method, with the body it usually takes and the headers it reads.
+pub struct WebDav {
+    pub method: &'static str,
+    pub template: Option<&'static str>,
+    pub depth: bool,
+    pub destination: bool,
+}
+
+pub const WEBDAV: [WebDav; 9] = [
+    WebDav {
+        method: "PROPFIND",
+        template: Some(PROPFIND),
+        depth: true,
+        destination: false,
+    },
+    WebDav {
+        method: "PROPPATCH",
+        template: Some(PROPPATCH),
+        depth: false,
+        destination: false,
+    },
+    WebDav {
+        method: "MKCOL",
+        template: None,
+        depth: false,
+        destination: false,
+    },
+    WebDav {
+        method: "COPY",
+        template: None,
+        depth: true,
+        destination: true,
+    },
+    WebDav {
+        method: "MOVE",
+        template: None,
+        depth: false,
+        destination: true,
+    },
+    WebDav {
+        method: "LOCK",
+        template: Some(LOCK),
+        depth: true,
+        destination: false,
+    },
+    WebDav {
+        method: "UNLOCK",
+        template: None,
+        depth: false,
+        destination: false,
+    },
+    WebDav {
+        method: "REPORT",
+        template: Some(REPORT),
+        depth: true,
+        destination: false,
+    },
+    WebDav {
+        method: "SEARCH",
+        template: Some(SEARCH),
+        depth: false,
+        destination: false,
+    },
+];
+
+const PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop>
+    <d:displayname/>
+    <d:getcontentlength/>
+    <d:getcontenttype/>
+    <d:getetag/>
+    <d:getlastmodified/>
+    <d:resourcetype/>
+  </d:prop>
+</d:propfind>"#;
+
+const PROPPATCH: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propertyupdate xmlns:d="DAV:">
+  <d:set>
+    <d:prop>
+      <d:displayname>New name</d:displayname>
+    </d:prop>
+  </d:set>
+</d:propertyupdate>"#;
+
+const LOCK: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:lockinfo xmlns:d="DAV:">
+  <d:lockscope><d:exclusive/></d:lockscope>
+  <d:locktype><d:write/></d:locktype>
+  <d:owner>cdghopt</d:owner>
+</d:lockinfo>"#;
 
+const REPORT: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data/>
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT"/>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
+
+const SEARCH: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:searchrequest xmlns:d="DAV:">
+  <d:basicsearch>
+    <d:select>
+      <d:prop><d:displayname/></d:prop>
+    </d:select>
+    <d:from>
+      <d:scope>
+        <d:href>/</d:href>
+        <d:depth>infinity</d:depth>
+      </d:scope>
+    </d:from>
+    <d:where>
+      <d:like>
+        <d:prop><d:displayname/></d:prop>
+        <d:literal>%</d:literal>
+      </d:like>
+    </d:where>
+  </d:basicsearch>
+</d:searchrequest>"#;
+
+pub fn webdav(method: &str) -> Option<&'static WebDav> {
+    WEBDAV.iter().find(|webdav| method == webdav.method)
+}
+
+/// Method of the request, any token is accepted. Standard and WebDAV methods are suggested along
+/// with the custom ones found in the history.
 #[component]
-pub fn MethodSelect(value: RwSignal<String>, class: &'static str) -> impl IntoView {
-    value.set("GET".to_string());
+pub fn MethodSelect(
+    value: RwSignal<String>,
+    indexes: RwSignal<Vec<LogIndexItem>>,
+    class: &'static str,
+) -> impl IntoView {
+    if value.with_untracked(String::is_empty) {
+        value.set("GET".to_string());
+    }
+    let list = format!("methods-{}", Uuid::now_v7().simple());
+    let custom = Signal::derive(move || {
+        indexes.with(|indexes| {
+            indexes
+                .iter()
+                .map(|index| index.method.clone())
+                .filter(|method| !METHODS.contains(&method.as_str()) && webdav(method).is_none())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        })
+    });
+    let valid = move || value.with(|method| is_token(method));
+    let refused = move || value.with(|method| forbidden::method(method));
+
     view! {
-        <select
+        <input
+            type="text"
+            class=class_list!["input", "rounded-none", "w-32", "font-mono", class]
+            class:input-error=move || !valid()
+            class:input-warning=refused
+            title=move || {
+                if !valid() {
+                    Some("Not a valid method name")
+                } else if refused() {
+                    Some("Browsers refuse to send this method")
+                } else {
+                    None
+                }
+            }
+            list=list.clone()
+            spellcheck="false"
             prop:value=value
-            class=class_list!["select", "rounded-none", class]
-            on:change=move |ev| {
-                let new_value = event_target_value(&ev);
-                value.set(new_value);
+            on:change=move |ev| value.set(normalize(event_target_value(&ev).trim()))
+        />
+        <datalist id=list>
+            {METHODS.map(|method| view! { <option value=method></option> })}
+            {WEBDAV.map(|webdav| view! { <option value=webdav.method>"WebDAV"</option> })}
+            <For
+                each=move || custom.get()
+                key=|method| method.clone()
+                children=|method| view! { <option value=method>"History"</option> }
+            />
+        </datalist>
+    }
+}
+
+/// `method` spelled like the standard or WebDAV method it names, whatever its case. Methods
+/// are case-sensitive, so others are kept as typed.
+fn normalize(method: &str) -> String {
+    METHODS
+        .into_iter()
+        .chain(WEBDAV.iter().map(|webdav| webdav.method))
+        .find(|known| known.eq_ignore_ascii_case(method))
+        .unwrap_or(method)
+        .to_string()
+}
+
+/// `Depth` and `Destination` inputs and the body template of a WebDAV `method`. Picking a
+/// method with a template fills an empty body with it.
+#[component]
+pub fn WebDavHelper(
+    method: RwSignal<String>,
+    header: RwSignal<Vec<(String, String)>>,
+    body: RwSignal<String>,
+    body_state: RwSignal<BodyState>,
+) -> impl IntoView {
+    let use_template = move |template: &str| {
+        body.set(template.to_string());
+        body_state.update(|state| {
+            state.mode = Mode::Raw;
+            state.language = Language::Xml;
+        });
+    };
+    Effect::new(move |previous: Option<String>| {
+        let current = method.get();
+        let changed = previous.is_some_and(|previous| previous != current);
+        if let Some(template) = webdav(&current).and_then(|webdav| webdav.template) {
+            if changed && body.with_untracked(|body| body.trim().is_empty()) {
+                use_template(template);
             }
-        >
-            <option value="CONNECT">CONNECT</option>
-            <option value="DELETE">DELETE</option>
-            <option value="GET" selected>
-                GET
-            </option>
-            <option value="HEAD">HEAD</option>
-            <option value="OPTIONS">OPTIONS</option>
-            <option value="PATCH">PATCH</option>
-            <option value="POST">POST</option>
-            <option value="PUT">PUT</option>
-            <option value="TRACE">TRACE</option>
-        </select>
+        }
+        current
+    });
+    let header_value = move |name: &'static str| {
+        header.with(|rows| {
+            rows.iter()
+                .find(|(row, _)| row.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default()
+        })
+    };
+    let set_header = move |name: &'static str, value: String| {
+        header.update(|rows| set_row(rows, name, value));
+    };
+
+    move || {
+        let webdav = method.with(|method| webdav(method))?;
+        Some(view! {
+            <div class="flex w-full items-center gap-2 px-1 py-2 text-sm join-item">
+                <span class="badge badge-outline">"WebDAV"</span>
+                <Show when=move || webdav.depth>
+                    <label class="flex items-center gap-1">
+                        "Depth"
+                        <select
+                            class="select select-xs"
+                            prop:value=move || header_value("depth")
+                            on:change=move |ev| set_header("depth", event_target_value(&ev))
+                        >
+                            <option value="">"-"</option>
+                            <option value="0">"0"</option>
+                            <option value="1">"1"</option>
+                            <option value="infinity">"infinity"</option>
+                        </select>
+                    </label>
+                </Show>
+                <Show when=move || webdav.destination>
+                    <input
+                        type="text"
+                        class="input input-xs w-full"
+                        placeholder="Destination URI..."
+                        prop:value=move || header_value("destination")
+                        on:change=move |ev| set_header("destination", event_target_value(&ev))
+                    />
+                </Show>
+                {webdav
+                    .template
+                    .map(|template| {
+                        view! {
+                            <button
+                                class="btn btn-xs ml-auto"
+                                title="Replace the body with a template"
+                                on:click=move |_| use_template(template)
+                            >
+                                "Body template"
+                            </button>
+                        }
+                    })}
+            </div>
+        })
+    }
+}
+
+/// Sets the header `name` in `rows`, an empty `value` removes it. The empty row the table ends
+/// with stays last.
+fn set_row(rows: &mut Vec<(String, String)>, name: &str, value: String) {
+    let existing = rows
+        .iter()
+        .position(|(row, _)| row.eq_ignore_ascii_case(name));
+    match existing {
+        Some(at) if value.is_empty() => {
+            rows.remove(at);
+        }
+        Some(at) => rows[at].1 = value,
+        None if value.is_empty() => {}
+        None => {
+            let at = match rows.last() {
+                Some((last, _)) if last.is_empty() => rows.len() - 1,
+                _ => rows.len(),
+            };
+            rows.insert(at, (name.to_string(), value));
+        }
+    }
+}
+
+/// Whether `method` is an RFC 9110 token, which is all a method has to be.
+fn is_token(method: &str) -> bool {
+    !method.is_empty()
+        && method
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        assert!(is_token("GET"));
+        assert!(is_token("VERSION-CONTROL"));
+        assert!(is_token("m!#$%&'*+.^_`|~1"));
+        assert!(!is_token(""));
+        assert!(!is_token("GET "));
+        assert!(!is_token("A/B"));
+        assert!(!is_token("MÉTHODE"));
+    }
+
+    #[test]
+    fn normalize_known_methods_only() {
+        assert_eq!(normalize("get"), "GET");
+        assert_eq!(normalize("propfind"), "PROPFIND");
+        assert_eq!(normalize("Purge"), "Purge");
     }
 }
@@ -0,0 +1,120 @@
+use leptos::prelude::*;
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag};
+
+/// Tags and markdown note of a history entry, with a form to edit them.
+#[component]
+pub fn Annotations(
+    tags: Vec<String>,
+    note: String,
+    on_save: impl Fn(Vec<String>, String) + Copy + Send + Sync + 'static,
+    on_tag: impl Fn(String) + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let editing = RwSignal::new(false);
+    let tags_draft = RwSignal::new(tags.join(", "));
+    let note_draft = RwSignal::new(note.clone());
+    let rendered = (!note.is_empty()).then(|| render(&note));
+
+    view! {
+        <div class="flex flex-wrap items-center gap-1">
+            {tags
+                .into_iter()
+                .map(|tag| {
+                    let label = tag.clone();
+                    view! {
+                        <div
+                            class="badge badge-sm badge-secondary cursor-pointer"
+                            title="Show entries with this tag"
+                            on:click=move |_| on_tag(tag.clone())
+                        >
+                            {label}
+                        </div>
+                    }
+                })
+                .collect_view()}
+            <button
+                class="btn btn-xs btn-ghost"
+                on:click=move |_| editing.update(|editing| *editing = !*editing)
+            >
+                {move || if editing.get() { "Cancel" } else { "Tags and note" }}
+            </button>
+        </div>
+        <Show
+            when=move || editing.get()
+            fallback={
+                let rendered = rendered.clone();
+                move || {
+                    rendered
+                        .clone()
+                        .map(|html| {
+                            view! { <div class="prose prose-sm max-w-none" inner_html=html></div> }
+                        })
+                }
+            }
+        >
+            <div class="flex flex-col gap-1">
+                <input
+                    type="text"
+                    class="input input-sm input-bordered"
+                    placeholder="Tags, comma separated"
+                    prop:value=tags_draft
+                    on:input=move |ev| tags_draft.set(event_target_value(&ev))
+                />
+                <textarea
+                    class="textarea textarea-bordered textarea-sm font-mono"
+                    placeholder="Note (markdown)"
+                    prop:value=note_draft
+                    on:input=move |ev| note_draft.set(event_target_value(&ev))
+                ></textarea>
+                <button
+                    class="btn btn-xs btn-primary self-end"
+                    on:click=move |_| {
+                        editing.set(false);
+                        on_save(parse_tags(&tags_draft.get_untracked()), note_draft.get_untracked());
+                    }
+                >
+                    Save
+                </button>
+            </div>
+        </Show>
+    }
+}
+
+/// Comma separated tags, trimmed, without empty or repeated ones.
+fn parse_tags(tags: &str) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+    for tag in tags.split(',').map(str::trim) {
+        if !tag.is_empty() && !parsed.iter().any(|parsed| parsed == tag) {
+            parsed.push(tag.to_string());
+        }
+    }
+    parsed
+}
+
+/// Markdown `note` as HTML. Raw HTML is shown as text and script links are dropped, notes end
+/// up in `inner_html`.
+fn render(note: &str) -> String {
+    let events = Parser::new(note).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if dest_url
+            .trim_start()
+            .to_ascii_lowercase()
+            .starts_with("javascript:") =>
+        {
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url: CowStr::Borrowed("#"),
+                title,
+                id,
+            })
+        }
+        event => event,
+    });
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events);
+    rendered
+}
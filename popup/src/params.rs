@@ -0,0 +1,427 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A query parameter, decoded. Disabled ones are left out of the URI but kept in the table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Param {
+    pub enabled: bool,
+    pub name: String,
+    pub value: String,
+}
+
+/// Path variable `:name` of the URI and what it is replaced with at send time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathVar {
+    pub name: String,
+    pub value: String,
+}
+
+/// Query parameters and path variables of `uri`, two-way synced with it.
+#[component]
+pub fn ParamsTable(
+    uri: RwSignal<String>,
+    params: RwSignal<Vec<Param>>,
+    path_vars: RwSignal<Vec<PathVar>>,
+) -> impl IntoView {
+    Effect::new(move |_| {
+        let uri = uri.get();
+        let merged = merge(&params.get_untracked(), parse_query(&uri));
+        if params.with_untracked(|params| *params != merged) {
+            params.set(merged);
+        }
+        let vars = path_var_names(&uri)
+            .into_iter()
+            .map(|name| {
+                let value = path_vars.with_untracked(|vars| {
+                    vars.iter()
+                        .find(|var| var.name == name)
+                        .map(|var| var.value.clone())
+                        .unwrap_or_default()
+                });
+                PathVar { name, value }
+            })
+            .collect::<Vec<_>>();
+        if path_vars.with_untracked(|old| *old != vars) {
+            path_vars.set(vars);
+        }
+    });
+
+    let edit = move |change: &dyn Fn(&mut Vec<Param>)| {
+        params.update(|params| change(params));
+        let rebuilt = with_query(&uri.get_untracked(), &params.get_untracked());
+        if uri.with_untracked(|uri| *uri != rebuilt) {
+            uri.set(rebuilt);
+        }
+    };
+
+    view! {
+        <table class="table table-xs w-full join-item">
+            <tbody>
+                <For
+                    each=move || params.get().into_iter().enumerate()
+                    key=|(index, param)| (*index, param.clone())
+                    children=move |(index, param)| {
+                        view! {
+                            <tr>
+                                <td class="w-6">
+                                    <input
+                                        type="checkbox"
+                                        class="checkbox checkbox-xs"
+                                        title="Send this parameter"
+                                        prop:checked=param.enabled
+                                        on:change=move |ev| {
+                                            let enabled = event_target_checked(&ev);
+                                            edit(&|params| params[index].enabled = enabled);
+                                        }
+                                    />
+                                </td>
+                                <td class="w-1/3">
+                                    <input
+                                        type="text"
+                                        class="input input-sm rounded-none w-full"
+                                        prop:value=param.name
+                                        on:change=move |ev| {
+                                            let name = event_target_value(&ev);
+                                            edit(&|params| params[index].name = name.clone());
+                                        }
+                                    />
+                                </td>
+                                <td>
+                                    <input
+                                        type="text"
+                                        class="input input-sm rounded-none w-full"
+                                        prop:value=param.value
+                                        on:change=move |ev| {
+                                            let value = event_target_value(&ev);
+                                            edit(&|params| params[index].value = value.clone());
+                                        }
+                                    />
+                                </td>
+                                <td class="w-6">
+                                    <button
+                                        class="btn btn-ghost btn-xs"
+                                        title="Remove"
+                                        on:click=move |_| {
+                                            edit(&|params| {
+                                                params.remove(index);
+                                            })
+                                        }
+                                    >
+                                        "✕"
+                                    </button>
+                                </td>
+                            </tr>
+                        }
+                    }
+                />
+                <tr>
+                    <td></td>
+                    <td colspan="3">
+                        <input
+                            type="text"
+                            class="input input-sm rounded-none w-full"
+                            placeholder="Add query parameter..."
+                            on:change=move |ev| {
+                                let name = event_target_value(&ev);
+                                if !name.is_empty() {
+                                    edit(&|params| {
+                                        params
+                                            .push(Param {
+                                                enabled: true,
+                                                name: name.clone(),
+                                                value: String::new(),
+                                            })
+                                    });
+                                }
+                                event_target::<web_sys::HtmlInputElement>(&ev).set_value("");
+                            }
+                        />
+                    </td>
+                </tr>
+                <For
+                    each=move || path_vars.get().into_iter().enumerate()
+                    key=|(index, var)| (*index, var.name.clone())
+                    children=move |(index, var)| {
+                        view! {
+                            <tr>
+                                <td></td>
+                                <td class="font-mono">{format!(":{}", var.name)}</td>
+                                <td colspan="2">
+                                    <input
+                                        type="text"
+                                        class="input input-sm rounded-none w-full"
+                                        placeholder="Path variable..."
+                                        prop:value=var.value
+                                        on:change=move |ev| {
+                                            let value = event_target_value(&ev);
+                                            path_vars
+                                                .update(|vars| {
+                                                    if let Some(var) = vars.get_mut(index) {
+                                                        var.value = value;
+                                                    }
+                                                });
+                                        }
+                                    />
+                                </td>
+                            </tr>
+                        }
+                    }
+                />
+            </tbody>
+        </table>
+    }
+}
+
+/// `uri` with its `:name` path segments replaced by the encoded values of `vars`. Variables
+/// without a value are left as they are.
+pub fn substitute(uri: &str, vars: &[PathVar]) -> String {
+    let (prefix, path, rest) = split(uri);
+    let path = path
+        .split('/')
+        .map(|segment| {
+            segment
+                .strip_prefix(':')
+                .and_then(|name| vars.iter().find(|var| var.name == name))
+                .filter(|var| !var.value.is_empty())
+                .map(|var| encode(&var.value))
+                .unwrap_or_else(|| segment.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("{prefix}{path}{rest}")
+}
+
+/// Keeps the disabled rows of `old` in place and fills the enabled ones with `parsed`, in order.
+fn merge(old: &[Param], parsed: Vec<Param>) -> Vec<Param> {
+    let mut parsed = parsed.into_iter();
+    let mut merged = Vec::with_capacity(old.len());
+    for param in old {
+        if !param.enabled {
+            merged.push(param.clone());
+        } else if let Some(param) = parsed.next() {
+            merged.push(param);
+        }
+    }
+    merged.extend(parsed);
+    merged
+}
+
+fn parse_query(uri: &str) -> Vec<Param> {
+    let Some((_, query)) = uri.split('#').next().and_then(|uri| uri.split_once('?')) else {
+        return Vec::new();
+    };
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Param {
+                enabled: true,
+                name: decode(name),
+                value: decode(value),
+            }
+        })
+        .collect()
+}
+
+/// `uri` with its query replaced by the enabled `params`.
+fn with_query(uri: &str, params: &[Param]) -> String {
+    let (uri, fragment) = match uri.split_once('#') {
+        Some((uri, fragment)) => (uri, format!("#{fragment}")),
+        None => (uri, String::new()),
+    };
+    let base = uri.split_once('?').map_or(uri, |(base, _)| base);
+    let query = params
+        .iter()
+        .filter(|param| param.enabled)
+        .map(|param| {
+            if param.value.is_empty() {
+                encode(&param.name)
+            } else {
+                format!("{}={}", encode(&param.name), encode(&param.value))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    if query.is_empty() {
+        format!("{base}{fragment}")
+    } else {
+        format!("{base}?{query}{fragment}")
+    }
+}
+
+fn path_var_names(uri: &str) -> Vec<String> {
+    let (_, path, _) = split(uri);
+    let mut names: Vec<String> = Vec::new();
+    for name in path
+        .split('/')
+        .filter_map(|segment| segment.strip_prefix(':'))
+    {
+        if !name.is_empty() && !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Scheme and authority, path, and query with fragment of `uri`.
+fn split(uri: &str) -> (&str, &str, &str) {
+    let authority = uri.find("://").map_or(0, |at| at + 3);
+    let end = uri[authority..]
+        .find(['?', '#'])
+        .map_or(uri.len(), |at| authority + at);
+    let path_start = uri[authority..end]
+        .find('/')
+        .map_or(end, |at| authority + at);
+    (&uri[..path_start], &uri[path_start..end], &uri[end..])
+}
+
+/// Percent-decodes a query component, `+` being a space. Malformed input is kept as it is.
+fn decode(component: &str) -> String {
+    let component = component.replace('+', " ");
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut at = 0;
+    while at < bytes.len() {
+        if b'%' != bytes[at] {
+            decoded.push(bytes[at]);
+            at += 1;
+            continue;
+        }
+        let byte = bytes
+            .get(at + 1..at + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        let Some(byte) = byte else {
+            return component;
+        };
+        decoded.push(byte);
+        at += 3;
+    }
+    String::from_utf8(decoded).unwrap_or(component)
+}
+
+/// Percent-encodes a URI component the way `encodeURIComponent` does.
+pub fn encode(component: &str) -> String {
+    let mut encoded = String::with_capacity(component.len());
+    for byte in component.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(enabled: bool, name: &str, value: &str) -> Param {
+        Param {
+            enabled,
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_query_decodes_pairs() {
+        let params = parse_query("https://a.test/p?a=1&&b=x+y&c=%20z%26&flag#x=2");
+        assert_eq!(
+            params,
+            vec![
+                param(true, "a", "1"),
+                param(true, "b", "x y"),
+                param(true, "c", " z&"),
+                param(true, "flag", ""),
+            ]
+        );
+        assert!(parse_query("https://a.test/p#a=1").is_empty());
+    }
+
+    #[test]
+    fn with_query_keeps_fragment_and_drops_disabled() {
+        let params = [
+            param(true, "q", "a b"),
+            param(false, "off", "1"),
+            param(true, "flag", ""),
+        ];
+        assert_eq!(
+            with_query("https://a.test/p?old=1#top", &params),
+            "https://a.test/p?q=a%20b&flag#top"
+        );
+        assert_eq!(
+            with_query("https://a.test/p?old=1#top", &[]),
+            "https://a.test/p#top"
+        );
+    }
+
+    #[test]
+    fn query_round_trips() {
+        let uri = "https://a.test/p?a=1&b=c%26d&e=%E2%82%AC#f";
+        assert_eq!(with_query(uri, &parse_query(uri)), uri);
+    }
+
+    #[test]
+    fn merge_keeps_disabled_rows_in_place() {
+        let old = [
+            param(true, "a", "1"),
+            param(false, "x", "0"),
+            param(true, "b", "2"),
+        ];
+        let parsed = vec![
+            param(true, "a", "3"),
+            param(true, "b", "4"),
+            param(true, "c", "5"),
+        ];
+        assert_eq!(
+            merge(&old, parsed),
+            vec![
+                param(true, "a", "3"),
+                param(false, "x", "0"),
+                param(true, "b", "4"),
+                param(true, "c", "5"),
+            ]
+        );
+        assert_eq!(merge(&old, Vec::new()), vec![param(false, "x", "0")]);
+    }
+
+    #[test]
+    fn substitute_fills_path_variables_only() {
+        let vars = [
+            PathVar {
+                name: "id".to_string(),
+                value: "a b/c".to_string(),
+            },
+            PathVar {
+                name: "post".to_string(),
+                value: String::new(),
+            },
+        ];
+        assert_eq!(
+            substitute("https://a.test/users/:id/posts/:post?q=:id#:id", &vars),
+            "https://a.test/users/a%20b%2Fc/posts/:post?q=:id#:id"
+        );
+    }
+
+    #[test]
+    fn split_finds_path_and_rest() {
+        assert_eq!(
+            split("https://a.test:8080/a/b?q=1#f"),
+            ("https://a.test:8080", "/a/b", "?q=1#f")
+        );
+        assert_eq!(split("https://a.test?q"), ("https://a.test", "", "?q"));
+        assert_eq!(split("https://a.test#f/g"), ("https://a.test", "", "#f/g"));
+    }
+
+    #[test]
+    fn encoding_matches_uri_components() {
+        assert_eq!(encode("€ &=+-_.!~*'()"), "%E2%82%AC%20%26%3D%2B-_.!~*'()");
+        assert_eq!(decode("%E2%82%AC+%2B"), "€ +");
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz+a"), "%zz a");
+        assert_eq!(decode("%FF"), "%FF");
+    }
+}
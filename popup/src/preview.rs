@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use http_types::Mime;
+use leptos::{html::Img, prelude::*};
+use wasm_bindgen::JsValue;
+use web_sys::{
+    js_sys::{Array, Uint8Array},
+    Blob, BlobPropertyBag, Url,
+};
+
+/// Whether `mime` can be previewed inline by [`MediaPreview`].
+pub fn is_media(mime: &Mime) -> bool {
+    matches!(mime.basetype(), "image" | "audio" | "video") || mime.essence() == "application/pdf"
+}
+
+#[component]
+pub fn MediaPreview(body: Arc<Vec<u8>>, mime: Mime) -> impl IntoView {
+    let size = body.len();
+    let url = match object_url(&body, mime.essence()) {
+        Ok(url) => url,
+        Err(e) => {
+            return view! { <div class="text-error">{format!("Failed to preview: {e:?}")}</div> }
+                .into_any();
+        }
+    };
+    on_cleanup({
+        let url = url.clone();
+        move || {
+            let _ = Url::revoke_object_url(&url);
+        }
+    });
+
+    let preview = match mime.basetype() {
+        "image" => {
+            let img: NodeRef<Img> = NodeRef::new();
+            let dimensions = RwSignal::new(None);
+            view! {
+                <div class="text-xs opacity-70">
+                    {move || dimensions.get().map(|(w, h)| format!("{w} × {h} px, "))}
+                    {format_size(size)}
+                </div>
+                <img
+                    node_ref=img
+                    src=url
+                    class="max-w-full"
+                    on:load=move |_| {
+                        if let Some(img) = img.get() {
+                            dimensions.set(Some((img.natural_width(), img.natural_height())));
+                        }
+                    }
+                />
+            }
+            .into_any()
+        }
+        "audio" => view! {
+            <div class="text-xs opacity-70">{format_size(size)}</div>
+            <audio src=url controls class="w-full"></audio>
+        }
+        .into_any(),
+        "video" => view! {
+            <div class="text-xs opacity-70">{format_size(size)}</div>
+            <video src=url controls class="max-w-full"></video>
+        }
+        .into_any(),
+        _ => view! {
+            <div class="text-xs opacity-70">{format_size(size)}</div>
+            <iframe src=url class="w-full h-screen"></iframe>
+        }
+        .into_any(),
+    };
+
+    view! { <div class="p-4">{preview}</div> }.into_any()
+}
+
+pub fn object_url(body: &[u8], mime: &str) -> Result<String, JsValue> {
+    let parts = Array::of1(&Uint8Array::from(body));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    Url::create_object_url_with_blob(&blob)
+}
+
+pub fn format_size(size: usize) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if 0 == unit {
+        format!("{size} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,780 @@
+use leptos::prelude::*;
+use module::http::Request;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
+
+use crate::body::{BodyState, Mode, PartValue};
+use crate::browser::{get_local, set_local};
+use crate::draft::TabDraft;
+use crate::log::LogContent;
+use crate::params::encode;
+
+/// Stands in for a redacted value. Plain ASCII, so it survives in query strings as is.
+pub const REDACTED: &str = "__REDACTED__";
+/// `storage.local` key of the [`Redaction`] rules.
+const HISTORY_REDACTION: &str = "history_redaction";
+
+/// Values masked before a request and its response are stored in the history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Redaction {
+    /// Header name patterns, `*` matches any run of characters, case-insensitive.
+    pub headers: Vec<String>,
+    /// Paths into JSON bodies, like `$.password`, `$.users[*].token` or `$["api-key"]`.
+    pub json_paths: Vec<String>,
+    /// Query parameter names, also masking form fields and multipart text parts.
+    pub query: Vec<String>,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            headers: vec![
+                "authorization".to_string(),
+                "*cookie".to_string(),
+                "x-api-key".to_string(),
+            ],
+            json_paths: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+}
+
+impl Redaction {
+    pub fn apply(&self, content: &mut LogContent) {
+        let request = &mut content.request;
+        for (name, value) in request.header.iter_mut() {
+            if self.is_secret_header(name) {
+                *value = REDACTED.to_string();
+            }
+        }
+        request.uri = self.redact_query(&request.uri);
+        self.redact_body(&mut request.body);
+        self.redact_form(content);
+
+        if let Some(response) = &mut content.response {
+            for (name, value) in response.header.iter_mut() {
+                if self.is_secret_header(name) {
+                    *value = REDACTED.as_bytes().to_vec();
+                }
+            }
+            response.uri = self.redact_query(&response.uri);
+            self.redact_body(&mut response.body);
+        }
+        if let Some(error) = &mut content.error {
+            *error = self.redact_urls(error);
+        }
+    }
+
+    /// Masks a draft kept in the clear, while the history is locked and can not seal it.
+    pub fn apply_draft(&self, tab: &mut TabDraft) {
+        for (name, value) in tab.header.iter_mut() {
+            if self.is_secret_header(name) {
+                *value = REDACTED.to_string();
+            }
+        }
+        tab.uri = self.redact_query(&tab.uri);
+        for param in tab.params.iter_mut() {
+            if self.is_secret_param(&param.name) {
+                param.value = REDACTED.to_string();
+            }
+        }
+        let mut body = std::mem::take(&mut tab.body).into_bytes();
+        self.redact_body(&mut body);
+        tab.body = String::from_utf8_lossy(&body).into_owned();
+        self.redact_state(&mut tab.body_state);
+    }
+
+    fn is_secret_header(&self, name: &str) -> bool {
+        self.headers.iter().any(|pattern| glob(pattern, name))
+    }
+
+    /// `text` with the query of every URL in it masked, for errors that quote the request URL.
+    fn redact_urls(&self, text: &str) -> String {
+        if self.query.is_empty() {
+            return text.to_string();
+        }
+        let mut redacted = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = [rest.find("http://"), rest.find("https://")]
+            .into_iter()
+            .flatten()
+            .min()
+        {
+            redacted.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            // Punctuation around a quoted URL is not part of it.
+            let url = rest[..end].trim_end_matches([')', ',', ':', '.']);
+            redacted.push_str(&self.redact_query(url));
+            rest = &rest[url.len()..];
+        }
+        redacted.push_str(rest);
+        redacted
+    }
+
+    fn redact_query(&self, uri: &str) -> String {
+        if self.query.is_empty() {
+            return uri.to_string();
+        }
+        let (uri, fragment) = uri
+            .split_once('#')
+            .map_or((uri, None), |(uri, f)| (uri, Some(f)));
+        let Some((path, query)) = uri.split_once('?') else {
+            return format!(
+                "{uri}{}",
+                fragment.map(|f| format!("#{f}")).unwrap_or_default()
+            );
+        };
+        let query = self.redact_pairs(query);
+        let fragment = fragment.map(|f| format!("#{f}")).unwrap_or_default();
+        format!("{path}?{query}{fragment}")
+    }
+
+    fn is_secret_param(&self, name: &str) -> bool {
+        self.query.iter().any(|secret| secret == name)
+    }
+
+    /// `name=value` pairs joined by `&`, with the values of secret names masked.
+    fn redact_pairs(&self, pairs: &str) -> String {
+        pairs
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if self.is_secret_param(name) => format!("{name}={REDACTED}"),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// Masks form fields and multipart text parts named like secret query parameters, in the
+    /// body state and in the body built from it.
+    fn redact_form(&self, content: &mut LogContent) {
+        if self.query.is_empty() {
+            return;
+        }
+        let state = &mut content.body_state;
+        let parts_redacted = self.redact_state(state);
+
+        let request = &mut content.request;
+        match state.mode {
+            Mode::Form => {
+                let body = String::from_utf8_lossy(&request.body);
+                request.body = self.redact_pairs(&body).into_bytes();
+            }
+            // The body is built again, so its boundary changes with it.
+            Mode::Multipart if parts_redacted => {
+                let (body, content_type) = state.encode(String::new());
+                request.body = body;
+                if let Some(content_type) = content_type {
+                    for (name, value) in request.header.iter_mut() {
+                        if name.eq_ignore_ascii_case("content-type") {
+                            *value = content_type.clone();
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Masks the form fields and multipart text parts of `state` named like secret query
+    /// parameters, and tells whether a part was masked.
+    fn redact_state(&self, state: &mut BodyState) -> bool {
+        for field in state.fields.iter_mut() {
+            if self.is_secret_param(&field.name) {
+                field.value = REDACTED.to_string();
+            }
+        }
+        let mut parts_redacted = false;
+        for part in state.parts.iter_mut() {
+            if let PartValue::Text(text) = &mut part.value {
+                if self.is_secret_param(&part.name) {
+                    *text = REDACTED.to_string();
+                    parts_redacted = true;
+                }
+            }
+        }
+        parts_redacted
+    }
+
+    /// Masks the JSON paths in `body`, leaving it untouched if it is not JSON or nothing
+    /// matched.
+    fn redact_body(&self, body: &mut Vec<u8>) {
+        if self.json_paths.is_empty() {
+            return;
+        }
+        let Ok(mut value) = serde_json::from_slice::<Value>(body) else {
+            return;
+        };
+        let mut redacted = false;
+        for path in self.json_paths.iter() {
+            if let Some(segments) = parse_path(path) {
+                redacted |= redact_value(&mut value, &segments);
+            }
+        }
+        if !redacted {
+            return;
+        }
+        let pretty = body.contains(&b'\n');
+        let written = if pretty {
+            serde_json::to_vec_pretty(&value)
+        } else {
+            serde_json::to_vec(&value)
+        };
+        if let Ok(written) = written {
+            *body = written;
+        }
+    }
+}
+
+pub async fn redaction() -> Redaction {
+    get_local(HISTORY_REDACTION).await.unwrap_or_default()
+}
+
+/// A redacted value of a request reopened from the history.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Secret {
+    Header(String),
+    Query(String),
+    /// JSON pointer and display path.
+    Body(String, String),
+    /// `application/x-www-form-urlencoded` field.
+    Form(String),
+    /// `multipart/form-data` text part.
+    Part(String),
+}
+
+impl Secret {
+    fn label(&self) -> String {
+        match self {
+            Self::Header(name) => format!("Header {name}"),
+            Self::Query(name) => format!("Query parameter {name}"),
+            Self::Body(_, path) => format!("Body {path}"),
+            Self::Form(name) => format!("Form field {name}"),
+            Self::Part(name) => format!("Part {name}"),
+        }
+    }
+}
+
+/// Redacted values in `request` that have to be filled in before it is sent again.
+pub fn secrets(request: &Request) -> Vec<Secret> {
+    let mut secrets = Vec::new();
+    let mut headers = request
+        .header
+        .iter()
+        .filter(|(_, value)| REDACTED == value.as_str())
+        .map(|(name, _)| Secret::Header(name.clone()))
+        .collect::<Vec<_>>();
+    headers.sort_by_key(Secret::label);
+    secrets.extend(headers);
+
+    if let Some((_, query)) = request
+        .uri
+        .split('#')
+        .next()
+        .and_then(|uri| uri.split_once('?'))
+    {
+        for pair in query.split('&') {
+            if let Some((name, REDACTED)) = pair.split_once('=') {
+                secrets.push(Secret::Query(name.to_string()));
+            }
+        }
+    }
+
+    if let Ok(value) = serde_json::from_slice::<Value>(&request.body) {
+        find_redacted(&value, String::new(), "$".to_string(), &mut secrets);
+    }
+
+    let content_type = request
+        .header
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.to_ascii_lowercase())
+        .unwrap_or_default();
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        for pair in String::from_utf8_lossy(&request.body).split('&') {
+            if let Some((name, REDACTED)) = pair.split_once('=') {
+                secrets.push(Secret::Form(name.to_string()));
+            }
+        }
+    } else if content_type.starts_with("multipart/form-data") {
+        let body = String::from_utf8_lossy(&request.body);
+        let marker = format!("\"\r\n\r\n{REDACTED}\r\n");
+        for (at, _) in body.match_indices(&marker) {
+            if let Some(start) = body[..at].rfind("name=\"") {
+                secrets.push(Secret::Part(body[start + 6..at].to_string()));
+            }
+        }
+    }
+    secrets
+}
+
+/// `request` with the `secrets` replaced by the `values` given for them.
+pub fn fill(mut request: Request, secrets: &[Secret], values: &[String]) -> Request {
+    let mut body = serde_json::from_slice::<Value>(&request.body).ok();
+    for (secret, value) in secrets.iter().zip(values) {
+        match secret {
+            Secret::Header(name) => {
+                request.header.insert(name.clone(), value.clone());
+            }
+            Secret::Query(name) => {
+                let (uri, fragment) = request
+                    .uri
+                    .split_once('#')
+                    .map_or((request.uri.as_str(), None), |(uri, f)| (uri, Some(f)));
+                if let Some((path, query)) = uri.split_once('?') {
+                    let query = fill_pairs(query, name, &encode(value));
+                    let fragment = fragment.map(|f| format!("#{f}")).unwrap_or_default();
+                    request.uri = format!("{path}?{query}{fragment}");
+                }
+            }
+            Secret::Body(pointer, _) => {
+                if let Some(target) = body.as_mut().and_then(|body| body.pointer_mut(pointer)) {
+                    *target = Value::String(value.clone());
+                }
+            }
+            Secret::Form(name) => {
+                let encoded = encode(value).replace("%20", "+");
+                let body = String::from_utf8_lossy(&request.body);
+                request.body = fill_pairs(&body, name, &encoded).into_bytes();
+            }
+            Secret::Part(name) => {
+                request.body = replace_bytes(
+                    &request.body,
+                    format!("name=\"{name}\"\r\n\r\n{REDACTED}\r\n").as_bytes(),
+                    format!("name=\"{name}\"\r\n\r\n{value}\r\n").as_bytes(),
+                );
+            }
+        }
+    }
+    let has_body_secret = secrets
+        .iter()
+        .any(|secret| matches!(secret, Secret::Body(..)));
+    if let (Some(body), true) = (body, has_body_secret) {
+        let pretty = request.body.contains(&b'\n');
+        let written = if pretty {
+            serde_json::to_vec_pretty(&body)
+        } else {
+            serde_json::to_vec(&body)
+        };
+        if let Ok(written) = written {
+            request.body = written;
+        }
+    }
+    request
+}
+
+/// `name=value` pairs joined by `&`, with the redacted values of `name` replaced by `encoded`.
+fn fill_pairs(pairs: &str, name: &str, encoded: &str) -> String {
+    pairs
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((pair_name, REDACTED)) if name == pair_name => format!("{name}={encoded}"),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Asks for the redacted values of `pending` and hands the filled request to `on_send`.
+#[component]
+pub fn SecretPrompt(
+    pending: RwSignal<Option<(Request, Vec<Secret>)>>,
+    on_send: impl Fn(Request) + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let values = RwSignal::new(Vec::<String>::new());
+    Effect::new(move |_| {
+        let count =
+            pending.with(|pending| pending.as_ref().map_or(0, |(_, secrets)| secrets.len()));
+        values.set(vec![String::new(); count]);
+    });
+
+    view! {
+        <Show when=move || pending.with(Option::is_some)>
+            <div class="modal modal-open">
+                <div class="modal-box flex flex-col gap-2">
+                    <h3 class="font-bold">Redacted values</h3>
+                    <p class="text-sm opacity-70">
+                        "This request was stored with secrets masked. Fill them in to send it."
+                    </p>
+                    {move || {
+                        pending
+                            .get()
+                            .map(|(_, secrets)| {
+                                secrets
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(i, secret)| {
+                                        view! {
+                                            <label class="form-control">
+                                                <span class="label-text">{secret.label()}</span>
+                                                <input
+                                                    type="password"
+                                                    class="input input-sm input-bordered"
+                                                    on:input=move |ev| {
+                                                        let value = event_target_value(&ev);
+                                                        values
+                                                            .update(|values| {
+                                                                if let Some(slot) = values.get_mut(i) {
+                                                                    *slot = value;
+                                                                }
+                                                            });
+                                                    }
+                                                />
+                                            </label>
+                                        }
+                                    })
+                                    .collect_view()
+                            })
+                    }}
+                    <div class="modal-action">
+                        <button class="btn btn-sm" on:click=move |_| pending.set(None)>
+                            Cancel
+                        </button>
+                        <button
+                            class="btn btn-sm btn-primary"
+                            on:click=move |_| {
+                                if let Some((request, secrets)) = pending.get_untracked() {
+                                    pending.set(None);
+                                    on_send(fill(request, &secrets, &values.get_untracked()));
+                                }
+                            }
+                        >
+                            Send
+                        </button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+/// Edits the [`Redaction`] rules.
+#[component]
+pub fn RedactionSettings() -> impl IntoView {
+    let rules = RwSignal::new(None::<Redaction>);
+    leptos::task::spawn_local(async move {
+        rules.set(Some(redaction().await));
+    });
+    let save = Action::new_local(move |_: &()| {
+        let rules = rules.get_untracked().unwrap_or_default();
+        async move {
+            let _ = set_local(HISTORY_REDACTION, &rules)
+                .await
+                .inspect_err(|e| error!("Failed to save redaction rules: {e:?}"));
+        }
+    });
+
+    let list = move |label: &'static str, field: fn(&mut Redaction) -> &mut Vec<String>| {
+        view! {
+            <label class="form-control">
+                <span class="label-text">{label}</span>
+                <textarea
+                    class="textarea textarea-bordered textarea-sm font-mono"
+                    prop:value=move || {
+                        rules
+                            .get()
+                            .map(|mut rules| field(&mut rules).join("\n"))
+                            .unwrap_or_default()
+                    }
+                    on:change=move |ev| {
+                        let lines = event_target_value(&ev)
+                            .lines()
+                            .map(str::trim)
+                            .filter(|line| !line.is_empty())
+                            .map(str::to_string)
+                            .collect::<Vec<_>>();
+                        rules
+                            .update(|rules| {
+                                if let Some(rules) = rules {
+                                    *field(rules) = lines;
+                                }
+                            });
+                    }
+                ></textarea>
+            </label>
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-2">
+            <span class="label-text font-bold">Redaction</span>
+            <span class="text-xs opacity-70">
+                {format!(
+                    "Matching values are stored as {REDACTED}, one rule per line. Reopened requests ask for them before sending.",
+                )}
+            </span>
+            {list("Header names (* wildcard)", |rules| &mut rules.headers)}
+            {list("JSON body paths", |rules| &mut rules.json_paths)}
+            {list("Query parameters and form fields", |rules| &mut rules.query)}
+            <button
+                class="btn btn-sm btn-primary self-end"
+                disabled=move || save.pending().get()
+                on:click=move |_| {
+                    save.dispatch(());
+                }
+            >
+                Save rules
+            </button>
+        </div>
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Any,
+}
+
+/// Parses `$.a.b[0]["c d"][*].*` style paths.
+fn parse_path(path: &str) -> Option<Vec<Segment>> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let (key, after) = after.split_at(end);
+            segments.push(match key {
+                "*" => Segment::Any,
+                "" => return None,
+                key => Segment::Key(key.to_string()),
+            });
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = if let Some(quoted) = after.strip_prefix('"') {
+                quoted.find('"')? + 2
+            } else {
+                after.find(']')?
+            };
+            let (inner, after) = after.split_at(end);
+            segments.push(match inner {
+                "*" => Segment::Any,
+                inner if inner.starts_with('"') => Segment::Key(serde_json::from_str(inner).ok()?),
+                inner => Segment::Index(inner.parse().ok()?),
+            });
+            rest = after.strip_prefix(']')?;
+        } else {
+            return None;
+        }
+    }
+    Some(segments)
+}
+
+/// Replaces whatever `segments` lead to in `value`, returning whether anything was.
+fn redact_value(value: &mut Value, segments: &[Segment]) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        *value = Value::String(REDACTED.to_string());
+        return true;
+    };
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(object)) => object
+            .get_mut(key)
+            .is_some_and(|value| redact_value(value, rest)),
+        (Segment::Index(i), Value::Array(array)) => array
+            .get_mut(*i)
+            .is_some_and(|value| redact_value(value, rest)),
+        // Every match is redacted, so no short-circuiting `any`.
+        (Segment::Any, Value::Object(object)) => object
+            .values_mut()
+            .map(|value| redact_value(value, rest))
+            .fold(false, |redacted, one| redacted | one),
+        (Segment::Any, Value::Array(array)) => array
+            .iter_mut()
+            .map(|value| redact_value(value, rest))
+            .fold(false, |redacted, one| redacted | one),
+        _ => false,
+    }
+}
+
+fn find_redacted(value: &Value, pointer: String, path: String, secrets: &mut Vec<Secret>) {
+    match value {
+        Value::String(s) if REDACTED == s => secrets.push(Secret::Body(pointer, path)),
+        Value::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                find_redacted(
+                    value,
+                    format!("{pointer}/{i}"),
+                    format!("{path}[{i}]"),
+                    secrets,
+                );
+            }
+        }
+        Value::Object(object) => {
+            for (key, value) in object {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                let child = format!("{path}[{}]", serde_json::to_string(key).unwrap_or_default());
+                find_redacted(value, format!("{pointer}/{escaped}"), child, secrets);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn replace_bytes(haystack: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut replaced = Vec::with_capacity(haystack.len());
+    let mut rest = haystack;
+    while let Some(at) = rest.windows(from.len()).position(|window| window == from) {
+        replaced.extend_from_slice(&rest[..at]);
+        replaced.extend_from_slice(to);
+        rest = &rest[at + from.len()..];
+    }
+    replaced.extend_from_slice(rest);
+    replaced
+}
+
+/// Case-insensitive match of `name` against `pattern`, where `*` matches any run of
+/// characters.
+fn glob(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let name = name.to_ascii_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(uri: &str, header: &[(&str, &str)], body: &str) -> Request {
+        let header = header
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Request::new("POST".to_string(), uri.to_string(), header, body.into())
+    }
+
+    fn redaction(query: &[&str]) -> Redaction {
+        Redaction {
+            query: query.iter().map(|name| name.to_string()).collect(),
+            ..Redaction::default()
+        }
+    }
+
+    #[test]
+    fn redact_pairs_masks_exact_names() {
+        let redaction = redaction(&["token"]);
+        assert_eq!(
+            redaction.redact_pairs("token=abc&xtoken=1&a=2&token"),
+            format!("token={REDACTED}&xtoken=1&a=2&token")
+        );
+        assert_eq!(
+            redaction.redact_query("https://a.test/p?token=abc#token=def"),
+            format!("https://a.test/p?token={REDACTED}#token=def")
+        );
+    }
+
+    #[test]
+    fn redact_urls_in_errors() {
+        let redaction = redaction(&["token"]);
+        assert_eq!(
+            redaction.redact_urls(
+                "error sending request for url (https://a.test/p?token=abc&a=1): timed out"
+            ),
+            format!(
+                "error sending request for url (https://a.test/p?token={REDACTED}&a=1): timed out"
+            )
+        );
+        assert_eq!(redaction.redact_urls("no url here"), "no url here");
+    }
+
+    #[test]
+    fn fill_query_encodes_the_exact_parameter() {
+        let request = stored(
+            &format!("https://a.test/p?xtoken={REDACTED}&token={REDACTED}#f"),
+            &[],
+            "",
+        );
+        let found = secrets(&request);
+        assert_eq!(
+            found,
+            [
+                Secret::Query("xtoken".to_string()),
+                Secret::Query("token".to_string())
+            ]
+        );
+        let filled = fill(request, &found[1..], &["a b&c".to_string()]);
+        assert_eq!(
+            filled.uri,
+            format!("https://a.test/p?xtoken={REDACTED}&token=a%20b%26c#f")
+        );
+    }
+
+    #[test]
+    fn fill_form_and_header() {
+        let request = stored(
+            "https://a.test",
+            &[
+                ("Authorization", REDACTED),
+                ("Content-Type", "application/x-www-form-urlencoded"),
+            ],
+            &format!("ba={REDACTED}&a={REDACTED}"),
+        );
+        let found = secrets(&request);
+        assert_eq!(
+            found,
+            [
+                Secret::Header("Authorization".to_string()),
+                Secret::Form("ba".to_string()),
+                Secret::Form("a".to_string()),
+            ]
+        );
+        let filled = fill(
+            request,
+            &[found[0].clone(), found[2].clone()],
+            &["Bearer t".to_string(), "x y".to_string()],
+        );
+        assert_eq!(filled.header["Authorization"], "Bearer t");
+        assert_eq!(filled.body, format!("ba={REDACTED}&a=x+y").into_bytes());
+    }
+
+    #[test]
+    fn fill_json_and_part() {
+        let request = stored(
+            "https://a.test",
+            &[],
+            &format!(r#"{{"user":{{"password":"{REDACTED}"}}}}"#),
+        );
+        let found = secrets(&request);
+        assert_eq!(
+            found,
+            [Secret::Body(
+                "/user/password".to_string(),
+                r#"$["user"]["password"]"#.to_string()
+            )]
+        );
+        let filled = fill(request, &found, &["p\"w".to_string()]);
+        assert_eq!(filled.body, br#"{"user":{"password":"p\"w"}}"#);
+
+        let request = stored(
+            "https://a.test",
+            &[("Content-Type", "multipart/form-data; boundary=b")],
+            &format!(
+                "--b\r\nContent-Disposition: form-data; name=\"key\"\r\n\r\n{REDACTED}\r\n--b--\r\n"
+            ),
+        );
+        let found = secrets(&request);
+        assert_eq!(found, [Secret::Part("key".to_string())]);
+        let filled = fill(request, &found, &["k".to_string()]);
+        assert_eq!(
+            filled.body,
+            b"--b\r\nContent-Disposition: form-data; name=\"key\"\r\n\r\nk\r\n--b--\r\n"
+        );
+    }
+}
@@ -1,11 +1,13 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use encoding::{
     all::{ASCII, GB18030, GBK, ISO_8859_1, UTF_8},
     DecoderTrap, Encoding,
 };
 use http::{header::CONTENT_TYPE, HeaderValue, StatusCode};
 use http_types::Mime;
+use jaq_json::Val;
 use leptos::prelude::*;
 use module::http::Response;
 use serde_json::Value;
@@ -13,24 +15,31 @@ use snafu::Snafu;
 use time::{macros::format_description, OffsetDateTime};
 use tracing::error;
 
+use crate::{
+    filter,
+    hex::HexView,
+    json::JsonTree,
+    preview::{is_media, MediaPreview},
+    sandbox::HtmlPreview,
+};
+
 #[component]
-pub fn ResponseView(resp: Response) -> impl IntoView {
+pub fn ResponseView(resp: Response, filter: RwSignal<String>) -> impl IntoView {
     let Response {
         done_date,
         status,
         header,
         body,
         elapsed_time,
+        uri,
     } = resp;
 
     let mut header_map = HashMap::new();
     for (name, value) in header.into_iter() {
         let value_str = HeaderValue::from_bytes(&value)
-            .unwrap()
-            .to_str()
-            .unwrap_or("no visible")
-            .to_string();
-        // TODO base 64
+            .ok()
+            .and_then(|v| v.to_str().ok().map(|v| v.to_string()))
+            .ok_or(value);
         header_map.insert(name, value_str);
     }
 
@@ -38,7 +47,8 @@ pub fn ResponseView(resp: Response) -> impl IntoView {
 
     let content_type = header_map
         .get(CONTENT_TYPE.as_str())
-        .map(|c| Mime::from_str(c))
+        .and_then(|c| c.as_deref().ok())
+        .map(Mime::from_str)
         .transpose()
         .unwrap_or(None);
 
@@ -47,50 +57,230 @@ pub fn ResponseView(resp: Response) -> impl IntoView {
         <div class="divider h-0"></div>
         <Header header=header_map />
         <div class="divider h-0"></div>
-        <Body content_type=content_type body=body />
+        <Body content_type=content_type body=body base=uri filter=filter />
     }
 }
 
-#[component]
-fn Body(body: Vec<u8>, content_type: Option<Mime>) -> impl IntoView {
-    let body = if let Some(content_type) = content_type {
-        let base = content_type.basetype();
-        let sub = content_type.subtype();
-        if "text" == base
-            || matches!(sub, "json" | "x-www-form-urlencoded" | "markdown" | "rtf")
-            || sub.contains("xml")
-        {
-            let body = if "json" == sub {
-                serde_json::from_slice::<Value>(&body)
-                    .and_then(|body| serde_json::to_vec_pretty(&body))
-                    .unwrap_or(body)
-            } else {
-                body
-            };
-
-            let charset = content_type
-                .param("charset")
-                .map(|c| c.as_str().to_lowercase())
-                .unwrap_or("utf-8".to_string());
-
-            decode(body, &charset)
-        } else {
-            Err(Error::NoText)
+/// Pause in typing before a jq filter is run.
+const FILTER_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BodyMode {
+    Tree,
+    Pretty,
+    Preview,
+    Html,
+    Text,
+    Raw,
+}
+
+impl BodyMode {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Tree => "Tree",
+            Self::Pretty => "Pretty",
+            Self::Preview | Self::Html => "Preview",
+            Self::Text => "Text",
+            Self::Raw => "Raw",
         }
+    }
+}
+
+#[component]
+fn Body(
+    body: Vec<u8>,
+    content_type: Option<Mime>,
+    base: String,
+    filter: RwSignal<String>,
+) -> impl IntoView {
+    let json = content_type
+        .as_ref()
+        .filter(|c| is_json(c))
+        .and_then(|_| serde_json::from_slice::<Value>(&body).ok())
+        .map(Arc::new);
+    let media = content_type.clone().filter(is_media);
+    let body = Arc::new(body);
+
+    let modes = if json.is_some() {
+        vec![BodyMode::Tree, BodyMode::Pretty, BodyMode::Raw]
+    } else if media.is_some() {
+        vec![BodyMode::Preview, BodyMode::Raw]
+    } else if content_type
+        .as_ref()
+        .is_some_and(|c| "text/html" == c.essence())
+    {
+        vec![BodyMode::Text, BodyMode::Html, BodyMode::Raw]
+    } else if content_type.as_ref().is_none_or(is_text) {
+        vec![BodyMode::Text, BodyMode::Raw]
     } else {
-        decode(body, "utf-8")
+        vec![BodyMode::Raw]
+    };
+    let mode = RwSignal::new(modes[0]);
+
+    let draft = RwSignal::new(filter.get_untracked());
+    // The filter run, `draft` after a pause in typing.
+    let code = RwSignal::new(filter.get_untracked());
+    let generation = StoredValue::new(0usize);
+    let on_draft = move |text: String| {
+        draft.set(text);
+        generation.update_value(|generation| *generation += 1);
+        let current = generation.get_value();
+        set_timeout(
+            move || {
+                if current == generation.get_value() {
+                    code.set(draft.get_untracked());
+                }
+            },
+            FILTER_DEBOUNCE,
+        );
     };
+    let input = StoredValue::new_local(None::<Val>);
+    let output = json.map(|json| {
+        Memo::new(move |_| {
+            let code = code.get();
+            if code.trim().is_empty() {
+                return Ok(json.clone());
+            }
+            if input.with_value(Option::is_none) {
+                input.set_value(Some(filter::input(&json)));
+            }
+            let input = input.with_value(|input| input.clone().unwrap_or_default());
+            filter::run(&code, &input).map(|mut outputs| {
+                if 1 == outputs.len() {
+                    Arc::new(outputs.remove(0))
+                } else {
+                    Arc::new(Value::Array(outputs))
+                }
+            })
+        })
+    });
 
-    // TODO raw body
     view! {
-        <pre class="p-4 rounded-md w-full overflow-x-auto">
-            <code>{body}</code>
-        </pre>
+        <div role="tablist" class="tabs tabs-boxed tabs-xs w-fit">
+            {modes
+                .into_iter()
+                .map(|m| {
+                    view! {
+                        <a
+                            role="tab"
+                            class=move || tab_class(m == mode.get())
+                            on:click=move |_| mode.set(m)
+                        >
+                            {m.label()}
+                        </a>
+                    }
+                })
+                .collect_view()}
+        </div>
+        {move || {
+            let mode = mode.get();
+            match (mode, output, media.clone()) {
+                (BodyMode::Tree | BodyMode::Pretty, Some(output), _) => {
+                    view! {
+                        <input
+                            type="text"
+                            placeholder="jq filter, e.g. .data[].id"
+                            prop:value=draft
+                            on:input=move |ev| on_draft(event_target_value(&ev))
+                            on:change=move |_| {
+                                let draft = draft.get_untracked();
+                                if code.with_untracked(|code| *code != draft) {
+                                    code.set(draft.clone());
+                                }
+                                filter.set(draft);
+                            }
+                            class="input input-sm rounded-none w-full font-mono"
+                        />
+                        <div class="text-error text-xs whitespace-pre-wrap">
+                            {move || output.get().err()}
+                        </div>
+                        {move || {
+                            let Ok(json) = output.get() else {
+                                return ().into_any();
+                            };
+                            if BodyMode::Tree == mode {
+                                view! { <JsonTree value=json /> }.into_any()
+                            } else {
+                                let body = serde_json::to_string_pretty(json.as_ref())
+                                    .unwrap_or_default();
+                                view! {
+                                    <pre class="p-4 rounded-md w-full overflow-x-auto">
+                                        <code>{body}</code>
+                                    </pre>
+                                }
+                                    .into_any()
+                            }
+                        }}
+                    }
+                        .into_any()
+                }
+                (BodyMode::Preview, _, Some(mime)) => {
+                    view! { <MediaPreview body=body.clone() mime=mime /> }.into_any()
+                }
+                (BodyMode::Html, _, _) => {
+                    let html = text(body.to_vec(), content_type.clone()).unwrap_or_default();
+                    view! { <HtmlPreview html=html base=base.clone() /> }.into_any()
+                }
+                (BodyMode::Text, _, _) => {
+                    let body = text(body.to_vec(), content_type.clone());
+                    view! {
+                        <pre class="p-4 rounded-md w-full overflow-x-auto">
+                            <code>{body}</code>
+                        </pre>
+                    }
+                        .into_any()
+                }
+                _ => view! { <HexView body=body.clone() /> }.into_any(),
+            }
+        }}
+    }
+}
+
+fn tab_class(active: bool) -> &'static str {
+    if active {
+        "tab tab-active"
+    } else {
+        "tab"
     }
 }
 
+/// `json` and its structured syntax suffix, like `application/vnd.api+json`.
+fn is_json(content_type: &Mime) -> bool {
+    let sub = content_type.subtype();
+    "json" == sub || sub.ends_with("+json")
+}
+
+fn is_text(content_type: &Mime) -> bool {
+    let sub = content_type.subtype();
+    "text" == content_type.basetype()
+        || is_json(content_type)
+        || matches!(sub, "x-www-form-urlencoded" | "markdown" | "rtf")
+        || sub.contains("xml")
+}
+
+fn text(body: Vec<u8>, content_type: Option<Mime>) -> Result<String, Error> {
+    let Some(content_type) = content_type else {
+        return decode(body, "utf-8");
+    };
+
+    let body = if is_json(&content_type) {
+        serde_json::from_slice::<Value>(&body)
+            .and_then(|body| serde_json::to_vec_pretty(&body))
+            .unwrap_or(body)
+    } else {
+        body
+    };
+
+    let charset = content_type
+        .param("charset")
+        .map(|c| c.as_str().to_lowercase())
+        .unwrap_or("utf-8".to_string());
+
+    decode(body, &charset)
+}
+
 #[component]
-fn Header(header: HashMap<String, String>) -> impl IntoView {
+fn Header(header: HashMap<String, Result<String, Vec<u8>>>) -> impl IntoView {
     view! {
         <div class="collapse collapse-arrow">
             <input type="checkbox" />
@@ -104,7 +294,12 @@ fn Header(header: HashMap<String, String>) -> impl IntoView {
                                 view! {
                                     <tr>
                                         <td>{h.0}</td>
-                                        <td>{h.1}</td>
+                                        <td>
+                                            {match h.1 {
+                                                Ok(value) => value.into_any(),
+                                                Err(value) => view! { <BinaryValue value=value /> }.into_any(),
+                                            }}
+                                        </td>
                                     </tr>
                                 }
                             })
@@ -116,6 +311,25 @@ fn Header(header: HashMap<String, String>) -> impl IntoView {
     }
 }
 
+/// Header value that is not visible ASCII, shown as escaped bytes or base64.
+#[component]
+fn BinaryValue(value: Vec<u8>) -> impl IntoView {
+    let base64 = RwSignal::new(false);
+    let escaped = value.escape_ascii().to_string();
+    let encoded = STANDARD.encode(&value);
+    view! {
+        <span class="font-mono break-all">
+            {move || if base64.get() { encoded.clone() } else { escaped.clone() }}
+        </span>
+        <button
+            class="badge badge-ghost badge-xs ml-1"
+            on:click=move |_| base64.update(|base64| *base64 = !*base64)
+        >
+            {move || if base64.get() { "base64" } else { "escaped" }}
+        </button>
+    }
+}
+
 #[component]
 fn Stat(status: StatusCode, elapsed_time: i32, done_date: OffsetDateTime) -> impl IntoView {
     let (color_class, status_icon) = if status.is_success() {
@@ -216,6 +430,4 @@ fn decode(text: Vec<u8>, charset: &str) -> Result<String, Error> {
 enum Error {
     #[snafu(display("Failed to decoding: {src}"), context(suffix(false)))]
     Decoding { src: String },
-    #[snafu(display("No text body"), context(suffix(false)))]
-    NoText,
 }
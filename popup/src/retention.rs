@@ -0,0 +1,400 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use indexed_db_futures::prelude::*;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use time::{ext::NumericalDuration, OffsetDateTime};
+use tracing::error;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::js_sys::Reflect;
+
+use crate::browser::{get_local, set_local};
+use crate::db::{from_js, open, CONTENTS, INDEXES};
+use crate::log::{open_index, prune, LogIndexItem};
+use crate::preview::format_size;
+use crate::redact::RedactionSettings;
+use crate::vault::{unlocked_key, Record};
+
+/// `storage.local` key of the [`Retention`] policy.
+const HISTORY_RETENTION: &str = "history_retention";
+/// Entries listed by the dashboard as the largest.
+const LARGEST: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Retention {
+    /// Unstarred items older than this many days are deleted, 0 keeps them.
+    pub max_age_days: u32,
+    /// Unstarred items kept, newest first, 0 for no limit.
+    pub max_count: u32,
+    /// Megabytes of unstarred contents kept, newest first, 0 for no limit.
+    pub max_megabytes: u32,
+    /// Hosts, subdomains included, whose requests are never stored.
+    pub never_store: Vec<String>,
+    /// Kilobytes of a body kept in history, the rest is cut off, 0 for no limit.
+    pub max_body_kilobytes: u32,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            max_age_days: 90,
+            max_count: 0,
+            max_megabytes: 0,
+            never_store: Vec::new(),
+            max_body_kilobytes: 0,
+        }
+    }
+}
+
+impl Retention {
+    pub fn cutoff(&self) -> Option<OffsetDateTime> {
+        (0 != self.max_age_days)
+            .then(|| OffsetDateTime::now_utc() - (self.max_age_days as i64).days())
+    }
+
+    pub fn max_count(&self) -> Option<usize> {
+        (0 != self.max_count).then_some(self.max_count as usize)
+    }
+
+    pub fn max_bytes(&self) -> Option<usize> {
+        (0 != self.max_megabytes).then_some(self.max_megabytes as usize * 1024 * 1024)
+    }
+
+    pub fn max_body_bytes(&self) -> Option<usize> {
+        (0 != self.max_body_kilobytes).then_some(self.max_body_kilobytes as usize * 1024)
+    }
+
+    pub fn never_stores(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.never_store.iter().any(|never| {
+            let never = never.to_ascii_lowercase();
+            host == never || host.ends_with(&format!(".{never}"))
+        })
+    }
+}
+
+pub async fn retention() -> Retention {
+    get_local(HISTORY_RETENTION).await.unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Default)]
+struct Report {
+    /// Bytes used by the extension and available to it, as estimated by the browser.
+    usage: Option<f64>,
+    quota: Option<f64>,
+    entries: usize,
+    content_bytes: usize,
+    /// Largest entries, with [`LogIndexItem::bytes`] freshly measured.
+    largest: Vec<LogIndexItem>,
+    /// Content keys without an index item.
+    orphans: Vec<String>,
+    orphan_bytes: usize,
+}
+
+/// Retention settings and what the history takes in storage. `on_pruned` runs after the
+/// settings were saved and applied.
+#[component]
+pub fn StorageDashboard(on_pruned: impl Fn() + Copy + 'static) -> impl IntoView {
+    let settings = RwSignal::new(None::<Retention>);
+    leptos::task::spawn_local(async move {
+        settings.set(Some(retention().await));
+    });
+    let edit = move |change: &dyn Fn(&mut Retention)| {
+        settings.update(|settings| {
+            if let Some(settings) = settings {
+                change(settings);
+            }
+        })
+    };
+    let number = move |get: fn(&Retention) -> u32| {
+        move || settings.with(|settings| settings.as_ref().map(get).unwrap_or_default())
+    };
+
+    let report = RwSignal::new(None::<Report>);
+    let scan = Action::new_local(move |_: &()| async move {
+        if let Ok(scanned) = scan()
+            .await
+            .inspect_err(|e| error!("Failed to scan storage: {e:?}"))
+        {
+            report.set(Some(scanned));
+        }
+    });
+    scan.dispatch(());
+
+    let apply = Action::new_local(move |_: &()| {
+        let retention = settings.get_untracked().unwrap_or_default();
+        async move {
+            let result = async {
+                set_local(HISTORY_RETENTION, &retention).await?;
+                prune(&retention).await
+            };
+            let _ = result
+                .await
+                .inspect_err(|e| error!("Failed to apply retention: {e:?}"));
+            on_pruned();
+            scan.dispatch(());
+        }
+    });
+    let collect = Action::new_local(move |_: &()| {
+        let orphans = report.with_untracked(|report| {
+            report
+                .as_ref()
+                .map(|report| report.orphans.clone())
+                .unwrap_or_default()
+        });
+        async move {
+            let _ = collect(orphans)
+                .await
+                .inspect_err(|e| error!("Failed to collect orphans: {e:?}"));
+            scan.dispatch(());
+        }
+    });
+
+    view! {
+        <div class="flex flex-col gap-4 p-2">
+            <div class="grid grid-cols-4 gap-2">
+                <label class="form-control">
+                    <span class="label-text">Max age (days)</span>
+                    <input
+                        type="number"
+                        min="0"
+                        class="input input-sm input-bordered"
+                        prop:value=number(|settings| settings.max_age_days)
+                        on:change=move |ev| {
+                            let days = event_target_value(&ev).parse().unwrap_or_default();
+                            edit(&|settings| settings.max_age_days = days);
+                        }
+                    />
+                </label>
+                <label class="form-control">
+                    <span class="label-text">Max entries</span>
+                    <input
+                        type="number"
+                        min="0"
+                        class="input input-sm input-bordered"
+                        prop:value=number(|settings| settings.max_count)
+                        on:change=move |ev| {
+                            let count = event_target_value(&ev).parse().unwrap_or_default();
+                            edit(&|settings| settings.max_count = count);
+                        }
+                    />
+                </label>
+                <label class="form-control">
+                    <span class="label-text">Max size (MB)</span>
+                    <input
+                        type="number"
+                        min="0"
+                        class="input input-sm input-bordered"
+                        prop:value=number(|settings| settings.max_megabytes)
+                        on:change=move |ev| {
+                            let megabytes = event_target_value(&ev).parse().unwrap_or_default();
+                            edit(&|settings| settings.max_megabytes = megabytes);
+                        }
+                    />
+                </label>
+                <label class="form-control">
+                    <span class="label-text">Max body (KB)</span>
+                    <input
+                        type="number"
+                        min="0"
+                        class="input input-sm input-bordered"
+                        prop:value=number(|settings| settings.max_body_kilobytes)
+                        on:change=move |ev| {
+                            let kilobytes = event_target_value(&ev).parse().unwrap_or_default();
+                            edit(&|settings| settings.max_body_kilobytes = kilobytes);
+                        }
+                    />
+                </label>
+            </div>
+            <span class="text-xs opacity-70">
+                "0 means no limit. Starred entries are always kept. Longer bodies are cut in history, the open response stays whole."
+            </span>
+            <label class="form-control">
+                <span class="label-text">Never store (one host per line, subdomains included)</span>
+                <textarea
+                    class="textarea textarea-bordered textarea-sm"
+                    prop:value=move || {
+                        settings
+                            .with(|settings| {
+                                settings
+                                    .as_ref()
+                                    .map(|settings| settings.never_store.join("\n"))
+                                    .unwrap_or_default()
+                            })
+                    }
+                    on:change=move |ev| {
+                        let hosts = event_target_value(&ev)
+                            .lines()
+                            .map(str::trim)
+                            .filter(|host| !host.is_empty())
+                            .map(str::to_string)
+                            .collect::<Vec<_>>();
+                        edit(&|settings| settings.never_store = hosts.clone());
+                    }
+                ></textarea>
+            </label>
+            <button
+                class="btn btn-sm btn-primary self-end"
+                disabled=move || apply.pending().get()
+                on:click=move |_| {
+                    apply.dispatch(());
+                }
+            >
+                Save and apply
+            </button>
+
+            <div class="divider my-0"></div>
+            <RedactionSettings />
+            <div class="divider my-0"></div>
+            {move || {
+                report
+                    .get()
+                    .map(|report| {
+                        let used = match (report.usage, report.quota) {
+                            (Some(usage), Some(quota)) => {
+                                format!(
+                                    "{} used of {}",
+                                    format_size(usage as usize),
+                                    format_size(quota as usize),
+                                )
+                            }
+                            (Some(usage), None) => format!("{} used", format_size(usage as usize)),
+                            _ => "Usage unknown".to_string(),
+                        };
+                        let orphans = report.orphans.len();
+                        view! {
+                            <div class="stats stats-vertical shadow">
+                                <div class="stat">
+                                    <div class="stat-title">Storage</div>
+                                    <div class="stat-value text-lg">{used}</div>
+                                    <div class="stat-desc">
+                                        {format!(
+                                            "{} entries, {} of content",
+                                            report.entries,
+                                            format_size(report.content_bytes),
+                                        )}
+                                    </div>
+                                </div>
+                                <div class="stat">
+                                    <div class="stat-title">Orphaned contents</div>
+                                    <div class="stat-value text-lg">{orphans}</div>
+                                    <div class="stat-desc">{format_size(report.orphan_bytes)}</div>
+                                    <div class="stat-actions">
+                                        <button
+                                            class="btn btn-xs"
+                                            disabled=move || 0 == orphans || collect.pending().get()
+                                            on:click=move |_| {
+                                                collect.dispatch(());
+                                            }
+                                        >
+                                            Collect
+                                        </button>
+                                    </div>
+                                </div>
+                            </div>
+                            <table class="table table-xs">
+                                <thead>
+                                    <tr>
+                                        <th>Largest entries</th>
+                                        <th class="text-right">Size</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {report
+                                        .largest
+                                        .into_iter()
+                                        .map(|item| {
+                                            view! {
+                                                <tr>
+                                                    <td class="break-all">
+                                                        {format!("{} {}", item.method, item.uri)}
+                                                    </td>
+                                                    <td class="text-right whitespace-nowrap">
+                                                        {format_size(item.bytes)}
+                                                    </td>
+                                                </tr>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </tbody>
+                            </table>
+                        }
+                    })
+            }}
+        </div>
+    }
+}
+
+/// Measures every stored content, finding the largest and those left without an index item.
+async fn scan() -> Result<Report, JsValue> {
+    let mut report = Report::default();
+    (report.usage, report.quota) = estimate().await.unwrap_or_default();
+
+    let vault_key = unlocked_key().await;
+    let db = open().await?;
+    let tx = db.transaction_on_multi(&[INDEXES, CONTENTS])?;
+    let mut items = tx
+        .object_store(INDEXES)?
+        .get_all()?
+        .await?
+        .iter()
+        .map(|item| open_index(from_js(item)?, vault_key.as_ref()))
+        .map(|item| item.map(|item| (item.id.to_string(), item)))
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    report.entries = items.len();
+
+    let mut sized = Vec::new();
+    if let Some(cursor) = tx.object_store(CONTENTS)?.open_cursor()?.await? {
+        loop {
+            let key = cursor
+                .key()
+                .and_then(|key| key.as_string())
+                .unwrap_or_default();
+            let bytes = Record::from_js(cursor.value())?.stored_bytes();
+            match items.remove(&key) {
+                Some(mut item) => {
+                    item.bytes = bytes;
+                    report.content_bytes += bytes;
+                    sized.push(item);
+                }
+                None => {
+                    report.orphans.push(key);
+                    report.orphan_bytes += bytes;
+                }
+            }
+            if !cursor.continue_cursor()?.await? {
+                break;
+            }
+        }
+    }
+
+    sized.sort_by_key(|item| Reverse(item.bytes));
+    sized.truncate(LARGEST);
+    report.largest = sized;
+    Ok(report)
+}
+
+/// Deletes the content keys `orphans`.
+async fn collect(orphans: Vec<String>) -> Result<(), JsValue> {
+    let db = open().await?;
+    let tx = db.transaction_on_one_with_mode(CONTENTS, IdbTransactionMode::Readwrite)?;
+    let contents = tx.object_store(CONTENTS)?;
+    for key in orphans {
+        contents.delete_owned(key)?;
+    }
+    tx.await.into_result()?;
+    Ok(())
+}
+
+/// Bytes used and available, from `navigator.storage.estimate()`.
+async fn estimate() -> Result<(Option<f64>, Option<f64>), JsValue> {
+    let Some(window) = web_sys::window() else {
+        return Ok((None, None));
+    };
+    let estimate = JsFuture::from(window.navigator().storage().estimate()?).await?;
+    let usage = Reflect::get(&estimate, &JsValue::from_str("usage"))?.as_f64();
+    let quota = Reflect::get(&estimate, &JsValue::from_str("quota"))?.as_f64();
+    Ok((usage, quota))
+}
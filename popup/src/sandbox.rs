@@ -0,0 +1,58 @@
+use leptos::{html::Iframe, prelude::*};
+use serde::{ser::Serializer as _, Serialize};
+use serde_wasm_bindgen::Serializer;
+use tracing::error;
+
+#[derive(Serialize)]
+struct PreviewMessage<'a> {
+    html: &'a str,
+    base: &'a str,
+    scripts: bool,
+}
+
+/// Renders `html` in the extension's sandbox page, resolving relative assets against `base`.
+#[component]
+pub fn HtmlPreview(html: String, base: String) -> impl IntoView {
+    let frame: NodeRef<Iframe> = NodeRef::new();
+    let scripts = RwSignal::new(false);
+    let loaded = RwSignal::new(false);
+
+    Effect::new(move |_| {
+        let scripts = scripts.get();
+        if !loaded.get() {
+            return;
+        }
+        let Some(window) = frame.get().and_then(|frame| frame.content_window()) else {
+            return;
+        };
+
+        let message = PreviewMessage {
+            html: &html,
+            base: &base,
+            scripts,
+        };
+        let _ = Serializer::json_compatible()
+            .serialize_some(&message)
+            .map_err(|e| e.into())
+            .and_then(|message| window.post_message(&message, "*"))
+            .inspect_err(|e| error!("Failed to post preview: {e:?}"));
+    });
+
+    view! {
+        <label class="label cursor-pointer justify-start gap-2 px-4">
+            <input
+                type="checkbox"
+                class="toggle toggle-xs toggle-warning"
+                prop:checked=scripts
+                on:change=move |ev| scripts.set(event_target_checked(&ev))
+            />
+            <span class="label-text text-xs">Enable scripts (inside the sandbox)</span>
+        </label>
+        <iframe
+            node_ref=frame
+            src="sandbox.html"
+            class="w-full h-screen bg-white"
+            on:load=move |_| loaded.set(true)
+        ></iframe>
+    }
+}
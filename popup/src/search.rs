@@ -0,0 +1,306 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use time::{macros::format_description, Date, UtcOffset};
+
+use crate::log::{LogContent, LogIndexItem};
+use crate::method::METHODS;
+
+/// `storage.local` key of the history search, kept between sessions.
+pub const HISTORY_SEARCH: &str = "history_search";
+/// Bytes of body shown on each side of a full-text match.
+const SNIPPET_CONTEXT: usize = 40;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Search {
+    pub text: String,
+    /// Also look for `text` in the request and response bodies.
+    pub full_text: bool,
+    pub method: String,
+    pub host: String,
+    pub tag: String,
+    /// Hundreds digit of the status, 0 for any.
+    pub status_class: u16,
+    /// `[year]-[month]-[day]`, as given by a date input.
+    pub from: String,
+    pub to: String,
+    pub starred: bool,
+}
+
+impl Search {
+    pub fn is_empty(&self) -> bool {
+        *self
+            == Self {
+                full_text: self.full_text,
+                ..Self::default()
+            }
+    }
+
+    /// Whether `item` passes the filters, and unless searching bodies, whether its URI, tags or
+    /// note contain the text.
+    pub fn matches_index(&self, item: &LogIndexItem) -> bool {
+        if !self.method.is_empty() && self.method != item.method {
+            return false;
+        }
+        if !self.host.is_empty() && !contains(item.host.as_bytes(), &self.host) {
+            return false;
+        }
+        if !self.tag.is_empty()
+            && !item
+                .tags
+                .iter()
+                .any(|tag| tag.eq_ignore_ascii_case(&self.tag))
+        {
+            return false;
+        }
+        if 0 != self.status_class && self.status_class != item.status / 100 {
+            return false;
+        }
+        if self.starred && !item.star {
+            return false;
+        }
+
+        let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+        let date = item.done_date.to_offset(offset).date();
+        if parse_date(&self.from).is_some_and(|from| date < from) {
+            return false;
+        }
+        if parse_date(&self.to).is_some_and(|to| date > to) {
+            return false;
+        }
+
+        self.full_text || self.in_index(item)
+    }
+
+    /// Whether the bodies of `item` have to be read to know if it is a hit.
+    pub fn needs_content(&self, item: &LogIndexItem) -> bool {
+        self.full_text && !self.text.is_empty() && !self.in_index(item)
+    }
+
+    fn in_index(&self, item: &LogIndexItem) -> bool {
+        contains(item.uri.as_bytes(), &self.text)
+            || item
+                .tags
+                .iter()
+                .any(|tag| contains(tag.as_bytes(), &self.text))
+            || contains(item.note.as_bytes(), &self.text)
+    }
+
+    /// The part of a body of `content` around the text, if one contains it.
+    pub fn snippet(&self, content: &LogContent) -> Option<String> {
+        let response = content.response.as_ref().map(|response| &response.body);
+        std::iter::once(&content.request.body)
+            .chain(response)
+            .find_map(|body| {
+                let at = find(body, &self.text)?;
+                let start = at.saturating_sub(SNIPPET_CONTEXT);
+                let end = body.len().min(at + self.text.len() + SNIPPET_CONTEXT);
+                let snippet = String::from_utf8_lossy(&body[start..end])
+                    .trim_matches('\u{FFFD}')
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Some(snippet)
+            })
+    }
+}
+
+#[component]
+pub fn SearchBar(
+    search: RwSignal<Option<Search>>,
+    hosts: Signal<Vec<String>>,
+    tags: Signal<Vec<String>>,
+) -> impl IntoView {
+    let value = move || search.get().unwrap_or_default();
+    let edit = move |change: &dyn Fn(&mut Search)| {
+        search.update(|search| {
+            if let Some(search) = search {
+                change(search);
+            }
+        })
+    };
+
+    view! {
+        <div class="flex flex-col gap-2 p-2">
+            <label class="input input-sm input-bordered flex items-center gap-2">
+                <input
+                    type="search"
+                    class="grow"
+                    placeholder="Search URI, tags and notes"
+                    prop:value=move || value().text
+                    on:change=move |ev| {
+                        let text = event_target_value(&ev);
+                        edit(&|search| search.text = text.clone());
+                    }
+                />
+                <span class="label-text">bodies</span>
+                <input
+                    type="checkbox"
+                    class="toggle toggle-xs"
+                    prop:checked=move || value().full_text
+                    on:change=move |ev| {
+                        let checked = event_target_checked(&ev);
+                        edit(&|search| search.full_text = checked);
+                    }
+                />
+            </label>
+            <div class="flex flex-wrap gap-2">
+                <select
+                    class="select select-sm select-bordered"
+                    prop:value=move || value().method
+                    on:change=move |ev| {
+                        let method = event_target_value(&ev);
+                        edit(&|search| search.method = method.clone());
+                    }
+                >
+                    <option value="">Any method</option>
+                    {METHODS.map(|method| view! { <option value=method>{method}</option> })}
+                </select>
+                <input
+                    type="text"
+                    class="input input-sm input-bordered w-32"
+                    placeholder="Host"
+                    list="history-hosts"
+                    prop:value=move || value().host
+                    on:change=move |ev| {
+                        let host = event_target_value(&ev);
+                        edit(&|search| search.host = host.clone());
+                    }
+                />
+                <datalist id="history-hosts">
+                    <For
+                        each=move || hosts.get()
+                        key=|host| host.clone()
+                        children=|host| view! { <option value=host></option> }
+                    />
+                </datalist>
+                <input
+                    type="text"
+                    class="input input-sm input-bordered w-32"
+                    placeholder="Tag"
+                    list="history-tags"
+                    prop:value=move || value().tag
+                    on:change=move |ev| {
+                        let tag = event_target_value(&ev);
+                        edit(&|search| search.tag = tag.clone());
+                    }
+                />
+                <datalist id="history-tags">
+                    <For
+                        each=move || tags.get()
+                        key=|tag| tag.clone()
+                        children=|tag| view! { <option value=tag></option> }
+                    />
+                </datalist>
+                <select
+                    class="select select-sm select-bordered"
+                    prop:value=move || value().status_class.to_string()
+                    on:change=move |ev| {
+                        let class = event_target_value(&ev).parse().unwrap_or_default();
+                        edit(&|search| search.status_class = class);
+                    }
+                >
+                    <option value="0">Any status</option>
+                    {(1..=5u16)
+                        .map(|class| {
+                            view! { <option value=class.to_string()>{format!("{class}xx")}</option> }
+                        })
+                        .collect_view()}
+                </select>
+                <input
+                    type="date"
+                    class="input input-sm input-bordered"
+                    title="From"
+                    prop:value=move || value().from
+                    on:change=move |ev| {
+                        let from = event_target_value(&ev);
+                        edit(&|search| search.from = from.clone());
+                    }
+                />
+                <input
+                    type="date"
+                    class="input input-sm input-bordered"
+                    title="To"
+                    prop:value=move || value().to
+                    on:change=move |ev| {
+                        let to = event_target_value(&ev);
+                        edit(&|search| search.to = to.clone());
+                    }
+                />
+                <label class="label cursor-pointer gap-2">
+                    <span class="label-text">Starred</span>
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || value().starred
+                        on:change=move |ev| {
+                            let checked = event_target_checked(&ev);
+                            edit(&|search| search.starred = checked);
+                        }
+                    />
+                </label>
+                <Show when=move || !value().is_empty()>
+                    <button
+                        class="btn btn-sm btn-ghost"
+                        on:click=move |_| {
+                            edit(&|search| {
+                                *search = Search {
+                                    full_text: search.full_text,
+                                    ..Search::default()
+                                }
+                            });
+                        }
+                    >
+                        Clear
+                    </button>
+                </Show>
+            </div>
+        </div>
+    }
+}
+
+/// `text` with every case-insensitive occurrence of `needle` marked.
+#[component]
+pub fn Highlight(text: String, needle: String) -> impl IntoView {
+    let mut parts = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(at) = find(rest.as_bytes(), &needle) {
+        let (before, hit) = rest.split_at(at);
+        let (hit, after) = hit.split_at(needle.len());
+        parts.push((before.to_string(), false));
+        parts.push((hit.to_string(), true));
+        rest = after;
+    }
+    parts.push((rest.to_string(), false));
+
+    parts
+        .into_iter()
+        .map(|(part, hit)| {
+            if hit {
+                view! { <mark class="bg-warning text-warning-content">{part}</mark> }.into_any()
+            } else {
+                part.into_any()
+            }
+        })
+        .collect_view()
+}
+
+/// Position of the first ASCII case-insensitive occurrence of `needle` in `haystack`. Only
+/// ASCII bytes are folded, so a match always starts and ends on a char boundary.
+fn find(haystack: &[u8], needle: &str) -> Option<usize> {
+    let needle = needle.as_bytes();
+    if needle.is_empty() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    needle.is_empty() || find(haystack, needle).is_some()
+}
+
+fn parse_date(date: &str) -> Option<Date> {
+    Date::parse(date, format_description!("[year]-[month]-[day]")).ok()
+}
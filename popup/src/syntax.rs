@@ -0,0 +1,297 @@
+//! Line by line highlighting for the body editor. Every line is tokenized on its own, so a
+//! construct spanning lines, like an XML comment, is only colored on its first line.
+
+use crate::body::Language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    Plain,
+    Key,
+    String,
+    Number,
+    Keyword,
+    Punct,
+    Comment,
+    Tag,
+    Attr,
+}
+
+impl Token {
+    /// Same colors as the response [`JsonTree`](crate::json::JsonTree).
+    pub fn class(self) -> &'static str {
+        match self {
+            Self::Plain => "",
+            Self::Key | Self::Tag => "text-primary",
+            Self::String => "text-success",
+            Self::Number => "text-info",
+            Self::Keyword => "text-warning",
+            Self::Punct => "opacity-50",
+            Self::Comment => "opacity-50 italic",
+            Self::Attr => "text-secondary",
+        }
+    }
+}
+
+const GRAPHQL_KEYWORDS: &[&str] = &[
+    "query",
+    "mutation",
+    "subscription",
+    "fragment",
+    "on",
+    "type",
+    "input",
+    "enum",
+    "interface",
+    "union",
+    "scalar",
+    "schema",
+    "extend",
+    "directive",
+    "implements",
+    "true",
+    "false",
+    "null",
+];
+
+const JAVASCRIPT_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "return",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+];
+
+pub fn tokenize(language: Language, line: &str) -> Vec<(Token, &str)> {
+    match language {
+        Language::Text => vec![(Token::Plain, line)],
+        Language::Json => json(line),
+        Language::Xml | Language::Html => xml(line),
+        Language::Yaml => yaml(line),
+        Language::GraphQl => c_like(line, GRAPHQL_KEYWORDS, "#"),
+        Language::JavaScript => c_like(line, JAVASCRIPT_KEYWORDS, "//"),
+    }
+}
+
+fn json(line: &str) -> Vec<(Token, &str)> {
+    let mut tokens = Vec::new();
+    let mut at = 0;
+    while at < line.len() {
+        let rest = &line[at..];
+        let c = rest.chars().next().unwrap_or_default();
+        let (token, len) = match c {
+            '"' => {
+                let len = string_len(rest, '"');
+                let key = line[at + len..].trim_start().starts_with(':');
+                (if key { Token::Key } else { Token::String }, len)
+            }
+            '-' | '0'..='9' => (Token::Number, number_len(rest)),
+            '{' | '}' | '[' | ']' | ',' | ':' => (Token::Punct, 1),
+            c if c.is_ascii_alphabetic() => (Token::Keyword, word_len(rest)),
+            c => (Token::Plain, c.len_utf8()),
+        };
+        push(&mut tokens, token, &line[at..at + len]);
+        at += len;
+    }
+    tokens
+}
+
+fn xml(line: &str) -> Vec<(Token, &str)> {
+    let mut tokens = Vec::new();
+    let mut at = 0;
+    let mut in_tag = false;
+    while at < line.len() {
+        let rest = &line[at..];
+        let c = rest.chars().next().unwrap_or_default();
+        let (token, len) = if rest.starts_with("<!--") {
+            let len = rest.find("-->").map_or(rest.len(), |end| end + 3);
+            (Token::Comment, len)
+        } else if !in_tag && '<' == c {
+            in_tag = true;
+            let open = if rest.starts_with("</") { 2 } else { 1 };
+            push(&mut tokens, Token::Punct, &rest[..open]);
+            at += open;
+            let name = &line[at..];
+            let len = name
+                .find(|c: char| c.is_whitespace() || '>' == c || '/' == c)
+                .unwrap_or(name.len());
+            (Token::Tag, len)
+        } else if in_tag && ('>' == c || rest.starts_with("/>")) {
+            in_tag = false;
+            (Token::Punct, if '>' == c { 1 } else { 2 })
+        } else if in_tag && ('"' == c || '\'' == c) {
+            (Token::String, string_len(rest, c))
+        } else if in_tag && '=' == c {
+            (Token::Punct, 1)
+        } else if in_tag && !c.is_whitespace() {
+            let len = rest
+                .find(|c: char| c.is_whitespace() || '=' == c || '>' == c || '/' == c)
+                .unwrap_or(rest.len())
+                .max(c.len_utf8());
+            (Token::Attr, len)
+        } else if !in_tag {
+            let len = rest.find('<').unwrap_or(rest.len()).max(c.len_utf8());
+            (Token::Plain, len)
+        } else {
+            (Token::Plain, c.len_utf8())
+        };
+        if len > 0 {
+            push(&mut tokens, token, &line[at..at + len]);
+            at += len;
+        }
+    }
+    tokens
+}
+
+fn yaml(line: &str) -> Vec<(Token, &str)> {
+    let mut tokens = Vec::new();
+    let indent = line.len() - line.trim_start().len();
+    push(&mut tokens, Token::Plain, &line[..indent]);
+    let mut at = indent;
+    let rest = &line[at..];
+    if rest.starts_with('#') {
+        push(&mut tokens, Token::Comment, rest);
+        return tokens;
+    }
+    if rest.starts_with("- ") || "-" == rest {
+        push(&mut tokens, Token::Punct, &rest[..1]);
+        at += 1;
+    }
+    let rest = &line[at..];
+    if let Some(colon) = key_colon(rest) {
+        let key_start = rest.len() - rest.trim_start().len();
+        push(&mut tokens, Token::Plain, &rest[..key_start]);
+        push(&mut tokens, Token::Key, &rest[key_start..colon]);
+        push(&mut tokens, Token::Punct, &rest[colon..colon + 1]);
+        at += colon + 1;
+    }
+    let rest = &line[at..];
+    let (value, comment) = match rest.find(" #") {
+        Some(hash) => rest.split_at(hash),
+        None => (rest, ""),
+    };
+    let trimmed = value.trim();
+    let token = match trimmed {
+        "" => Token::Plain,
+        "true" | "false" | "null" | "~" | "yes" | "no" => Token::Keyword,
+        _ if trimmed.parse::<f64>().is_ok() => Token::Number,
+        _ if trimmed.starts_with(['|', '>', '&', '*', '!']) => Token::Attr,
+        _ => Token::String,
+    };
+    push(&mut tokens, token, value);
+    push(&mut tokens, Token::Comment, comment);
+    tokens
+}
+
+fn c_like<'a>(line: &'a str, keywords: &[&str], comment: &str) -> Vec<(Token, &'a str)> {
+    let mut tokens = Vec::new();
+    let mut at = 0;
+    while at < line.len() {
+        let rest = &line[at..];
+        let c = rest.chars().next().unwrap_or_default();
+        let (token, len) = if rest.starts_with(comment) {
+            (Token::Comment, rest.len())
+        } else {
+            match c {
+                '"' | '\'' | '`' => (Token::String, string_len(rest, c)),
+                '0'..='9' => (Token::Number, number_len(rest)),
+                '$' | '@' => (Token::Attr, 1 + word_len(&rest[1..])),
+                c if c.is_alphabetic() || '_' == c => {
+                    let len = word_len(rest);
+                    let word = &rest[..len];
+                    let token = if keywords.contains(&word) {
+                        Token::Keyword
+                    } else if line[at + len..].trim_start().starts_with(':') {
+                        Token::Key
+                    } else {
+                        Token::Plain
+                    };
+                    (token, len)
+                }
+                '{' | '}' | '(' | ')' | '[' | ']' | ',' | ':' | ';' | '=' | '!' | '.' => {
+                    (Token::Punct, 1)
+                }
+                c => (Token::Plain, c.len_utf8()),
+            }
+        };
+        push(&mut tokens, token, &line[at..at + len]);
+        at += len;
+    }
+    tokens
+}
+
+/// Appends `text` unless it is empty.
+fn push<'a>(tokens: &mut Vec<(Token, &'a str)>, token: Token, text: &'a str) {
+    if text.is_empty() {
+        return;
+    }
+    tokens.push((token, text));
+}
+
+/// Length of the string literal at the start of `text`, up to the closing `quote` or the end of
+/// the line.
+fn string_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (at, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return at + c.len_utf8(),
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+fn number_len(text: &str) -> usize {
+    text.find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+        .unwrap_or(text.len())
+        .max(1)
+}
+
+fn word_len(text: &str) -> usize {
+    text.find(|c: char| !(c.is_alphanumeric() || '_' == c))
+        .unwrap_or(text.len())
+}
+
+/// Position of the `:` ending a YAML mapping key at the start of `text`.
+fn key_colon(text: &str) -> Option<usize> {
+    let trimmed = text.trim_start();
+    if trimmed.starts_with(['"', '\'', '#', '[', '{']) {
+        return None;
+    }
+    let colon = text.find(':')?;
+    let after = &text[colon + 1..];
+    (after.is_empty() || after.starts_with(' ')).then_some(colon)
+}
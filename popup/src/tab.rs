@@ -0,0 +1,448 @@
+use std::future::Future;
+
+use http::Uri;
+use leptos::prelude::*;
+use module::http::{Phase, Progress, Request, Response};
+use tracing::error;
+use uuid::Uuid;
+use web_sys::js_sys::Date;
+
+use crate::body::{with_content_type, BodyEditor, BodyState, Mode};
+use crate::draft::TabDraft;
+use crate::header::HeaderTable;
+use crate::lint::{lint, LintPrompt};
+use crate::log::{save_error, save_filter, save_log, LogContent, LogIndexItem};
+use crate::method::{MethodSelect, WebDavHelper};
+use crate::params::{substitute, Param, ParamsTable, PathVar};
+use crate::preview::format_size;
+use crate::redact::{secrets, SecretPrompt, REDACTED};
+use crate::response::ResponseView;
+use crate::send::SendButton;
+use crate::transfer::{http_attach, http_send, Error};
+use crate::uri::UriInput;
+use crate::vault::get_content;
+
+/// Editor and response of one request tab. Every tab sends on its own, so several can be
+/// waiting at once.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTab {
+    pub id: Uuid,
+    pub method: RwSignal<String>,
+    pub uri: RwSignal<String>,
+    pub header: RwSignal<Vec<(String, String)>>,
+    /// Text of the raw body.
+    pub body: RwSignal<String>,
+    pub body_state: RwSignal<BodyState>,
+    pub filter: RwSignal<String>,
+    /// History entry of the response shown.
+    pub log_id: RwSignal<Option<Uuid>>,
+    pub params: RwSignal<Vec<Param>>,
+    pub path_vars: RwSignal<Vec<PathVar>>,
+    pub resp: RwSignal<Option<Result<Response, Error>>>,
+    pub progress: RwSignal<Option<Progress>>,
+    pub pending: RwSignal<bool>,
+    /// Id of the send still running in the background.
+    pub transfer: RwSignal<Option<String>>,
+}
+
+impl RequestTab {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            method: RwSignal::new("".to_string()),
+            uri: RwSignal::new("".to_string()),
+            header: RwSignal::new(vec![("".to_string(), "".to_string())]),
+            body: RwSignal::new("".to_string()),
+            body_state: RwSignal::new(BodyState::default()),
+            filter: RwSignal::new("".to_string()),
+            log_id: RwSignal::new(None),
+            params: RwSignal::new(Vec::new()),
+            path_vars: RwSignal::new(Vec::new()),
+            resp: RwSignal::new(None),
+            progress: RwSignal::new(None),
+            pending: RwSignal::new(false),
+            transfer: RwSignal::new(None),
+        }
+    }
+
+    /// A tab with the state of `draft`, its response is read back from the history.
+    pub fn from_draft(draft: TabDraft) -> Self {
+        let tab = Self::new();
+        let TabDraft {
+            id,
+            method,
+            uri,
+            mut header,
+            body,
+            filter,
+            log_id,
+            params,
+            path_vars,
+            body_state,
+            transfer,
+        } = draft;
+        if header.last().is_none_or(|(name, _)| !name.is_empty()) {
+            header.push(("".to_string(), "".to_string()));
+        }
+        tab.method.set(method);
+        tab.uri.set(uri);
+        tab.header.set(header);
+        tab.body.set(body);
+        tab.filter.set(filter);
+        tab.log_id.set(log_id);
+        tab.params.set(params);
+        tab.path_vars.set(path_vars);
+        tab.body_state.set(body_state);
+        tab.transfer.set(transfer);
+        if let Some(log_id) = log_id {
+            leptos::task::spawn_local(async move {
+                match get_content(&log_id.to_string()).await {
+                    Ok(Some(content)) => tab.resp.set(Some(response(content))),
+                    Ok(None) => {}
+                    Err(e) => error!("Failed to load response: {e:?}"),
+                }
+            });
+        }
+        Self { id, ..tab }
+    }
+
+    pub fn to_draft(self) -> TabDraft {
+        TabDraft {
+            id: self.id,
+            method: self.method.get_untracked(),
+            uri: self.uri.get_untracked(),
+            header: self.header.get_untracked(),
+            body: self.body.get_untracked(),
+            filter: self.filter.get_untracked(),
+            log_id: self.log_id.get_untracked(),
+            params: self.params.get_untracked(),
+            path_vars: self.path_vars.get_untracked(),
+            body_state: self.body_state.get_untracked(),
+            transfer: self.transfer.get_untracked(),
+        }
+    }
+
+    /// A new tab with the same request and response.
+    pub fn duplicate(self) -> Self {
+        let copy = Self::from_draft(TabDraft {
+            id: Uuid::now_v7(),
+            log_id: None,
+            transfer: None,
+            ..self.to_draft()
+        });
+        copy.log_id.set(self.log_id.get_untracked());
+        copy.resp.set(self.resp.get_untracked());
+        copy
+    }
+
+    /// Subscribes to every edit, for the autosave.
+    pub fn track(self) {
+        self.method.track();
+        self.uri.track();
+        self.header.track();
+        self.body.track();
+        self.filter.track();
+        self.log_id.track();
+        self.params.track();
+        self.path_vars.track();
+        self.body_state.track();
+        self.transfer.track();
+    }
+
+    /// Shows the history entry `id` with its request and response.
+    pub fn open(self, id: Uuid, content: LogContent) {
+        let LogContent {
+            request:
+                Request {
+                    method,
+                    uri,
+                    header,
+                    body,
+                },
+            filter,
+            body_state,
+            ..
+        } = content.clone();
+        self.method.set(method);
+        self.params.set(Vec::new());
+        self.uri.set(uri);
+        let mut header: Vec<(String, String)> = header.into_iter().collect();
+        header.push(("".to_string(), "".to_string()));
+        self.header.set(header);
+        // Only a raw body is text, the other modes are rebuilt from their state.
+        let raw = match body_state.mode {
+            Mode::Raw => String::from_utf8(body).unwrap_or_default(),
+            _ => String::new(),
+        };
+        self.body.set(raw);
+        self.body_state.set(body_state);
+        self.filter.set(filter);
+        self.log_id.set(Some(id));
+        self.resp.set(Some(response(content)));
+    }
+
+    /// Method and path, for the tab strip.
+    pub fn label(self) -> String {
+        let method = self.method.get();
+        let uri = self.uri.get();
+        if uri.is_empty() {
+            return "New request".to_string();
+        }
+        let path = uri
+            .parse::<Uri>()
+            .ok()
+            .map(|uri| uri.path().to_string())
+            .unwrap_or(uri);
+        format!("{method} {path}").trim().to_string()
+    }
+
+    /// The request the editor describes, with the content type of its body.
+    fn request(self) -> (Request, Option<String>) {
+        let state = self.body_state.get_untracked();
+        let (body, content_type) = state.encode(self.body.get_untracked());
+        let request = Request::new(
+            self.method.get_untracked(),
+            substitute(&self.uri.get_untracked(), &self.path_vars.get_untracked()),
+            with_content_type(
+                self.header.get_untracked(),
+                state.mode,
+                content_type.clone(),
+            ),
+            body,
+        );
+        (request, content_type)
+    }
+
+    /// Waits for the send a reloaded page left running, if any.
+    pub fn resume(self, indexes: RwSignal<Vec<LogIndexItem>>) {
+        if let Some(id) = self.transfer.get_untracked() {
+            let (req, _) = self.request();
+            self.finish(indexes, req, http_attach(id, self.progress));
+        }
+    }
+
+    fn send(self, indexes: RwSignal<Vec<LogIndexItem>>, req: Request) {
+        let id = Uuid::now_v7().to_string();
+        self.transfer.set(Some(id.clone()));
+        self.finish(indexes, req.clone(), http_send(id, req, self.progress));
+    }
+
+    /// Logs and shows the response of `req` once `response` resolves.
+    fn finish(
+        self,
+        indexes: RwSignal<Vec<LogIndexItem>>,
+        req: Request,
+        response: impl Future<Output = Result<Response, Error>> + 'static,
+    ) {
+        self.pending.set(true);
+        let body_state = self.body_state.get_untracked();
+        leptos::task::spawn_local(async move {
+            let started = Date::now();
+            let result = response.await;
+
+            let id = match &result {
+                Ok(resp) => {
+                    let filter = self.filter.get_untracked();
+                    save_log(indexes, req, body_state, resp.clone(), filter).await
+                }
+                Err(e) => {
+                    let elapsed_time = (Date::now() - started) as i32;
+                    save_error(indexes, req, body_state, e.to_string(), elapsed_time).await
+                }
+            };
+            self.log_id
+                .set(id.inspect_err(|e| error!("{e:?}")).ok().flatten());
+            self.resp.set(Some(result));
+            self.transfer.set(None);
+            self.pending.set(false);
+        });
+    }
+}
+
+/// The response of a history entry, or the error its send failed with.
+fn response(content: LogContent) -> Result<Response, Error> {
+    match (content.response, content.error) {
+        (Some(response), _) => Ok(response),
+        (None, error) => Err(Error::Logged {
+            src: error.unwrap_or_default(),
+        }),
+    }
+}
+
+#[component]
+pub fn RequestPane(
+    tab: RequestTab,
+    indexes: RwSignal<Vec<LogIndexItem>>,
+    on_discard: impl Fn() + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let RequestTab {
+        method,
+        uri,
+        header,
+        body,
+        body_state,
+        filter,
+        log_id,
+        params,
+        path_vars,
+        resp,
+        progress,
+        pending,
+        ..
+    } = tab;
+
+    let secret_pending = RwSignal::new(None);
+    let lint_pending = RwSignal::new(None);
+    let on_checked = move |request: Request| {
+        let secrets = secrets(&request);
+        if secrets.is_empty() {
+            tab.send(indexes, request);
+        } else {
+            secret_pending.set(Some((request, secrets)));
+        }
+    };
+    let on_submit = move |_| {
+        let (request, content_type) = tab.request();
+        let warnings =
+            body_state.with_untracked(|state| lint(&request, state, content_type.as_deref()));
+        if warnings.is_empty() {
+            on_checked(request);
+        } else {
+            lint_pending.set(Some((request, warnings)));
+        }
+    };
+    let on_secrets = move |request: Request| {
+        // Keeps path variables in the editor unless a query value was filled in.
+        if uri.with_untracked(|uri| uri.contains(REDACTED)) {
+            uri.set(request.uri.clone());
+        }
+        header.update(|rows| {
+            for (name, value) in rows.iter_mut() {
+                if let Some(filled) = request.header.get(name.as_str()) {
+                    value.clone_from(filled);
+                }
+            }
+        });
+        if Mode::Raw == body_state.with_untracked(|state| state.mode) {
+            body.set(String::from_utf8_lossy(&request.body).into_owned());
+        }
+        tab.send(indexes, request);
+    };
+
+    Effect::new(move |_| {
+        let filter = filter.get();
+        if let Some(id) = log_id.get_untracked() {
+            leptos::task::spawn_local(async move {
+                let _ = save_filter(id, filter)
+                    .await
+                    .inspect_err(|e| error!("Failed to save filter: {e:?}"));
+            });
+        }
+    });
+
+    view! {
+        <LintPrompt pending=lint_pending on_send=on_checked />
+        <SecretPrompt pending=secret_pending on_send=on_secrets />
+        <div class="grid grid-cols-2 gap-4">
+            <div class="p-4 min-h-screen">
+                <div class="join join-vertical rounded-none h-full w-full">
+                    <div class="join rounded-none w-full join-item">
+                        <MethodSelect value=method indexes=indexes class="join-item" />
+                        <UriInput value=uri class="w-full join-item" />
+                        <SendButton on:click=on_submit class="join-item" />
+                        <button
+                            class="btn btn-ghost join-item"
+                            title="Discard draft"
+                            on:click=move |_| on_discard()
+                        >
+                            Discard
+                        </button>
+                    </div>
+                    <WebDavHelper
+                        method=method
+                        header=header
+                        body=body
+                        body_state=body_state
+                    />
+                    <ParamsTable uri=uri params=params path_vars=path_vars />
+                    <div class="divider"></div>
+                    <HeaderTable rows=header class="w-full join-item" />
+                    <div class="divider"></div>
+                    <BodyEditor state=body_state raw=body />
+                </div>
+            </div>
+            <Show
+                when=move || { !pending.get() }
+                fallback=move || {
+                    view! {
+                        <div class="flex min-h-screen">
+                            <div class="m-auto flex flex-col items-center gap-2">
+                                <span class="loading loading-infinity loading-lg"></span>
+                                {move || {
+                                    progress
+                                        .get()
+                                        .map(|p| {
+                                            let text = match (p.phase, p.total) {
+                                                (Phase::Sending, _) => "Waiting for response".to_string(),
+                                                (Phase::Receiving, Some(total)) => {
+                                                    format!(
+                                                        "Received {} of {}",
+                                                        format_size(p.received as usize),
+                                                        format_size(total as usize),
+                                                    )
+                                                }
+                                                (Phase::Receiving, None) => {
+                                                    format!("Received {}", format_size(p.received as usize))
+                                                }
+                                            };
+                                            view! {
+                                                <progress
+                                                    class="progress w-56"
+                                                    value=p.total.map(|_| p.received.to_string())
+                                                    max=p.total.map(|t| t.to_string())
+                                                ></progress>
+                                                <span class="text-xs opacity-70">{text}</span>
+                                            }
+                                        })
+                                }}
+                            </div>
+                        </div>
+                    }
+                }
+            >
+                <div class="p-4">
+                    <ErrorBoundary fallback=|errors| {
+                        view! {
+                            <div role="alert" class="alert alert-error">
+                                <svg
+                                    xmlns="http://www.w3.org/2000/svg"
+                                    class="h-6 w-6 shrink-0 stroke-current"
+                                    fill="none"
+                                    viewBox="0 0 24 24"
+                                >
+                                    <path
+                                        stroke-linecap="round"
+                                        stroke-linejoin="round"
+                                        stroke-width="2"
+                                        d="M10 14l2-2m0 0l2-2m-2 2l-2-2m2 2l2 2m7-2a9 9 0 11-18 0 9 9 0 0118 0z"
+                                    />
+                                </svg>
+                                <span>
+                                    {move || {
+                                        errors
+                                            .get()
+                                            .into_iter()
+                                            .map(|(_, e)| view! { <li>{e.to_string()}</li> })
+                                            .collect_view()
+                                    }}
+                                </span>
+                            </div>
+                        }
+                    }>
+                        {move || resp.get().map(|r| r.map(|rr| view! { <ResponseView resp=rr filter=filter /> }))}
+                    </ErrorBoundary>
+                </div>
+            </Show>
+        </div>
+    }
+}
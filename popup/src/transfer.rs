@@ -0,0 +1,246 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+    time::Duration,
+};
+
+use futures::channel::oneshot;
+use leptos::prelude::*;
+use module::{
+    http::{Progress, Request, Response, Transfer},
+    Message, PORT_NAME,
+};
+use snafu::Snafu;
+use tracing::error;
+use wasm_bindgen::prelude::*;
+use web_extensions_sys::Port;
+
+use crate::browser::browser;
+
+/// Times the popup reconnects to a transfer after its port dropped.
+const RECONNECTS: usize = 3;
+/// Messages from the popup keep the background service worker awake.
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+
+#[wasm_bindgen]
+extern "C" {
+    /// [`Port`] whose `postMessage` throws once the other end has gone away.
+    type CheckedPort;
+
+    #[wasm_bindgen(catch, method, js_name = postMessage)]
+    fn post_message(this: &CheckedPort, value: &JsValue) -> Result<(), JsValue>;
+}
+
+#[derive(serde::Serialize)]
+struct ConnectInfo {
+    name: &'static str,
+}
+
+struct Connection {
+    port: Port,
+    _on_message: Closure<dyn Fn(JsValue)>,
+    _on_disconnect: Closure<dyn Fn(JsValue)>,
+}
+
+struct State {
+    id: String,
+    done: Option<oneshot::Sender<Result<Message, Error>>>,
+    progress: RwSignal<Option<Progress>>,
+    connection: Option<Connection>,
+    reconnects: usize,
+}
+
+/// Sends `req` through the background as the transfer `id` over a long-lived port, reporting
+/// `progress` until the response arrives. A dropped port is reconnected and reattached to the
+/// same transfer, the request itself is never sent twice.
+pub async fn http_send(
+    id: String,
+    req: Request,
+    progress: RwSignal<Option<Progress>>,
+) -> Result<Response, Error> {
+    let msg: Message = Transfer {
+        id: id.clone(),
+        request: req,
+    }
+    .try_into()
+    .map_err(serde_error)?;
+    transfer(id, msg, progress).await
+}
+
+/// Waits for the response of the transfer `id`, started by a page since reloaded.
+pub async fn http_attach(
+    id: String,
+    progress: RwSignal<Option<Progress>>,
+) -> Result<Response, Error> {
+    let msg = attach(&id);
+    transfer(id, msg, progress).await
+}
+
+async fn transfer(
+    id: String,
+    msg: Message,
+    progress: RwSignal<Option<Progress>>,
+) -> Result<Response, Error> {
+    let (done, result) = oneshot::channel();
+    let state = Rc::new(RefCell::new(State {
+        id,
+        done: Some(done),
+        progress,
+        connection: None,
+        reconnects: 0,
+    }));
+    connect(&state, &msg);
+
+    let ping = {
+        let state = Rc::downgrade(&state);
+        set_interval_with_handle(
+            move || {
+                let ping = Message {
+                    code: "ping".to_string(),
+                    value: String::new(),
+                };
+                if let Some(state) = state.upgrade() {
+                    if let Some(connection) = &state.borrow().connection {
+                        post(&connection.port, &ping);
+                    }
+                }
+            },
+            PING_INTERVAL,
+        )
+        .ok()
+    };
+
+    let result = result.await.unwrap_or(Err(Error::Send {
+        src: "The transfer was dropped".to_string(),
+    }));
+
+    if let Some(ping) = ping {
+        ping.clear();
+    }
+    progress.set(None);
+    if let Some(connection) = state.borrow_mut().connection.take() {
+        connection.port.disconnect();
+    }
+
+    let msg = result?;
+    serde_json::from_str::<Response>(&msg.value).map_err(serde_error)
+}
+
+fn connect(state: &Rc<RefCell<State>>, msg: &Message) {
+    let info = ConnectInfo { name: PORT_NAME };
+    let Ok(info) = serde_wasm_bindgen::to_value(&info) else {
+        return;
+    };
+    let port = browser().runtime().connect(None, info.unchecked_ref());
+
+    let on_message: Closure<dyn Fn(JsValue)> = {
+        let state = Rc::downgrade(state);
+        Closure::new(move |msg| on_message(&state, msg))
+    };
+    port.on_message()
+        .add_listener(on_message.as_ref().unchecked_ref());
+
+    let on_disconnect: Closure<dyn Fn(JsValue)> = {
+        let state = Rc::downgrade(state);
+        // Reconnect outside of the listener, it is dropped along with the old connection.
+        Closure::new(move |_| {
+            let state = state.clone();
+            leptos::task::spawn_local(async move { reconnect(&state) });
+        })
+    };
+    port.on_disconnect()
+        .add_listener(on_disconnect.as_ref().unchecked_ref());
+
+    post(&port, msg);
+    state.borrow_mut().connection = Some(Connection {
+        port,
+        _on_message: on_message,
+        _on_disconnect: on_disconnect,
+    });
+}
+
+fn on_message(state: &Weak<RefCell<State>>, msg: JsValue) {
+    let Some(state) = state.upgrade() else {
+        return;
+    };
+    let msg = match serde_wasm_bindgen::from_value::<Message>(msg) {
+        Ok(msg) => msg,
+        Err(e) => {
+            error!("{e}");
+            return;
+        }
+    };
+
+    let mut state = state.borrow_mut();
+    match msg.code.as_str() {
+        "progress" => {
+            if let Ok(progress) = serde_json::from_str(&msg.value) {
+                state.progress.set(Some(progress));
+            }
+        }
+        "error" => {
+            if let Some(done) = state.done.take() {
+                let _ = done.send(Err(Error::Send { src: msg.value }));
+            }
+        }
+        _ => {
+            if let Some(done) = state.done.take() {
+                let _ = done.send(Ok(msg));
+            }
+        }
+    }
+}
+
+fn reconnect(state: &Weak<RefCell<State>>) {
+    let Some(state) = state.upgrade() else {
+        return;
+    };
+
+    let attach = {
+        let mut state = state.borrow_mut();
+        state.connection = None;
+        if state.done.is_none() {
+            return;
+        }
+        if state.reconnects >= RECONNECTS {
+            if let Some(done) = state.done.take() {
+                let _ = done.send(Err(Error::Send {
+                    src: "Lost connection to the background".to_string(),
+                }));
+            }
+            return;
+        }
+        state.reconnects += 1;
+        attach(&state.id)
+    };
+    connect(&state, &attach);
+}
+
+fn attach(id: &str) -> Message {
+    Message {
+        code: "http_attach".to_string(),
+        value: id.to_string(),
+    }
+}
+
+fn post(port: &Port, msg: &Message) {
+    let _ = serde_wasm_bindgen::to_value(msg)
+        .map_err(JsValue::from)
+        .and_then(|msg| port.unchecked_ref::<CheckedPort>().post_message(&msg))
+        .inspect_err(|e| error!("Failed to post message: {e:?}"));
+}
+
+fn serde_error(e: impl std::error::Error) -> Error {
+    Error::Serialize { src: e.to_string() }
+}
+
+#[derive(Debug, Clone, Snafu)]
+pub enum Error {
+    #[snafu(display("Failed to send: {src}"), context(suffix(false)))]
+    Send { src: String },
+    #[snafu(display("{src}"), context(suffix(false)))]
+    Serialize { src: String },
+    /// An error reopened from the history.
+    #[snafu(display("{src}"), context(suffix(false)))]
+    Logged { src: String },
+}
@@ -0,0 +1,492 @@
+use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use indexed_db_futures::prelude::*;
+use leptos::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::error;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_extensions_sys::StorageChange;
+use web_sys::{
+    js_sys::{Array, ArrayBuffer, Object, Reflect, Uint8Array},
+    CryptoKey, Pbkdf2Params,
+};
+
+use crate::browser::{browser, get_local, js_error, set_local};
+use crate::compress::{pack, unpack};
+use crate::db::{from_js, open, to_js, CONTENTS, INDEXES};
+use crate::log::{index_record, open_index, LogContent};
+
+/// `storage.local` key of the [`Vault`] settings, present while encryption is on.
+const HISTORY_VAULT: &str = "history_vault";
+/// `storage.session` key of the unlocked key. Session storage lives in memory until the browser
+/// closes and is not exposed to content scripts.
+const HISTORY_KEY: &str = "history_key";
+/// Field of a sealed content record, holding nonce and ciphertext.
+const SEALED: &str = "sealed";
+const ITERATIONS: u32 = 600_000;
+const NONCE_LEN: usize = 12;
+/// Sealed with the key to tell a wrong passphrase.
+const CHECK: &[u8] = b"CDGHOPT";
+
+/// Encryption settings, the key itself is derived from the passphrase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Vault {
+    /// base64
+    salt: String,
+    iterations: u32,
+    /// [`CHECK`] sealed with the key, base64.
+    check: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultState {
+    Off,
+    Locked,
+    Unlocked,
+}
+
+/// AES-256-GCM key derived from the passphrase with PBKDF2-SHA256.
+#[derive(Clone)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    fn seal(&self, plain: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let mut nonce = [0; NONCE_LEN];
+        random(&mut nonce)?;
+        let cipher = Aes256Gcm::new((&self.0).into());
+        let sealed = cipher
+            .encrypt(Nonce::from_slice(&nonce), plain)
+            .map_err(|_| JsValue::from_str("Failed to encrypt"))?;
+        Ok([nonce.as_slice(), &sealed].concat())
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, JsValue> {
+        if sealed.len() < NONCE_LEN {
+            return Err(JsValue::from_str("Sealed content is truncated"));
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        let cipher = Aes256Gcm::new((&self.0).into());
+        cipher
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| JsValue::from_str("Failed to decrypt, wrong key"))
+    }
+
+    /// `value` as JSON sealed with the key, base64.
+    pub fn seal_value<T: Serialize>(&self, value: &T) -> Result<String, JsValue> {
+        let plain = serde_json::to_vec(value).map_err(js_error)?;
+        Ok(STANDARD.encode(self.seal(&plain)?))
+    }
+
+    pub fn open_value<T: DeserializeOwned>(&self, sealed: &str) -> Result<T, JsValue> {
+        let sealed = STANDARD.decode(sealed).map_err(js_error)?;
+        serde_json::from_slice(&self.open(&sealed)?).map_err(js_error)
+    }
+}
+
+/// A [`CONTENTS`] record, in the clear or sealed with the vault key. Its bodies are
+/// [packed](pack) either way, and only inflated when the record is opened.
+#[derive(Debug, Clone)]
+pub enum Record {
+    Plain(Box<LogContent>),
+    Sealed(Vec<u8>),
+}
+
+impl Record {
+    /// `content` sealed with `key`, or in the clear without one.
+    pub fn new(mut content: LogContent, key: Option<&Key>) -> Result<Self, JsValue> {
+        pack(&mut content);
+        match key {
+            Some(key) => {
+                let plain = serde_json::to_vec(&content).map_err(js_error)?;
+                Ok(Self::Sealed(key.seal(&plain)?))
+            }
+            None => Ok(Self::Plain(Box::new(content))),
+        }
+    }
+
+    pub fn from_js(value: JsValue) -> Result<Self, JsValue> {
+        let sealed = Reflect::get(&value, &JsValue::from_str(SEALED))?;
+        match sealed.dyn_into::<Uint8Array>() {
+            Ok(sealed) => Ok(Self::Sealed(sealed.to_vec())),
+            Err(_) => Ok(Self::Plain(Box::new(from_js(value)?))),
+        }
+    }
+
+    pub fn to_js(&self) -> Result<JsValue, JsValue> {
+        match self {
+            Self::Plain(content) => to_js(content),
+            Self::Sealed(sealed) => {
+                let record = Object::new();
+                Reflect::set(
+                    &record,
+                    &JsValue::from_str(SEALED),
+                    &Uint8Array::from(sealed.as_slice()),
+                )?;
+                Ok(record.into())
+            }
+        }
+    }
+
+    pub fn open(self, key: Option<&Key>) -> Result<LogContent, JsValue> {
+        let mut content = match (self, key) {
+            (Self::Plain(content), _) => *content,
+            (Self::Sealed(sealed), Some(key)) => {
+                serde_json::from_slice(&key.open(&sealed)?).map_err(js_error)?
+            }
+            (Self::Sealed(_), None) => return Err(JsValue::from_str("History is locked")),
+        };
+        unpack(&mut content)?;
+        Ok(content)
+    }
+
+    /// Approximate bytes taken in storage.
+    pub fn stored_bytes(&self) -> usize {
+        match self {
+            Self::Plain(content) => content.stored_bytes(),
+            Self::Sealed(sealed) => sealed.len(),
+        }
+    }
+}
+
+pub async fn state() -> VaultState {
+    if get_local::<Vault>(HISTORY_VAULT).await.is_err() {
+        VaultState::Off
+    } else if unlocked_key().await.is_some() {
+        VaultState::Unlocked
+    } else {
+        VaultState::Locked
+    }
+}
+
+/// The key of this browser session, if the vault was unlocked.
+pub async fn unlocked_key() -> Option<Key> {
+    let items = browser()
+        .storage()
+        .session()
+        .get(&JsValue::from_str(HISTORY_KEY))
+        .await
+        .ok()?;
+    let key = Reflect::get(&items, &JsValue::from_str(HISTORY_KEY))
+        .ok()?
+        .as_string()?;
+    STANDARD.decode(key).ok()?.try_into().ok().map(Key)
+}
+
+/// Runs `f` whenever the vault is unlocked, from this page or another one.
+pub fn on_unlock(f: impl Fn() + 'static) {
+    let on_changed: Closure<dyn Fn(JsValue, String)> =
+        Closure::new(move |changes: JsValue, area: String| {
+            if "session" != area {
+                return;
+            }
+            let Ok(change) = Reflect::get(&changes, &JsValue::from_str(HISTORY_KEY)) else {
+                return;
+            };
+            if change.is_undefined() {
+                return;
+            }
+            if !change
+                .unchecked_into::<StorageChange>()
+                .new_value()
+                .is_undefined()
+            {
+                f();
+            }
+        });
+    browser()
+        .storage()
+        .on_changed()
+        .add_listener(on_changed.as_ref().unchecked_ref());
+    on_changed.forget();
+}
+
+/// The key new contents are sealed with: none while encryption is off, an error while locked
+/// so nothing is written in the clear.
+pub async fn sealing_key() -> Result<Option<Key>, JsValue> {
+    match state().await {
+        VaultState::Off => Ok(None),
+        VaultState::Unlocked => Ok(unlocked_key().await),
+        VaultState::Locked => Err(JsValue::from_str("History is locked")),
+    }
+}
+
+pub async fn get_content(key: &str) -> Result<Option<LogContent>, JsValue> {
+    let vault_key = unlocked_key().await;
+    let db = open().await?;
+    let tx = db.transaction_on_one(CONTENTS)?;
+    let Some(value) = tx.object_store(CONTENTS)?.get_owned(key)?.await? else {
+        return Ok(None);
+    };
+    Record::from_js(value)?.open(vault_key.as_ref()).map(Some)
+}
+
+pub async fn put_content(key: &str, content: LogContent) -> Result<(), JsValue> {
+    let vault_key = sealing_key().await?;
+    let record = Record::new(content, vault_key.as_ref())?;
+    let db = open().await?;
+    let tx = db.transaction_on_one_with_mode(CONTENTS, IdbTransactionMode::Readwrite)?;
+    tx.object_store(CONTENTS)?
+        .put_key_val_owned(key, &record.to_js()?)?;
+    tx.await.into_result()?;
+    Ok(())
+}
+
+async fn enable(passphrase: &str) -> Result<(), JsValue> {
+    let mut salt = [0; 16];
+    random(&mut salt)?;
+    let key = derive(passphrase, &salt, ITERATIONS).await?;
+    let vault = Vault {
+        salt: STANDARD.encode(salt),
+        iterations: ITERATIONS,
+        check: STANDARD.encode(key.seal(CHECK)?),
+    };
+    set_local(HISTORY_VAULT, &vault).await?;
+    remember(&key).await?;
+    reseal(None, Some(&key)).await
+}
+
+async fn unlock(passphrase: &str) -> Result<(), JsValue> {
+    let vault: Vault = get_local(HISTORY_VAULT).await?;
+    let salt = STANDARD.decode(&vault.salt).map_err(js_error)?;
+    let check = STANDARD.decode(&vault.check).map_err(js_error)?;
+    let key = derive(passphrase, &salt, vault.iterations).await?;
+    if key.open(&check).ok().as_deref() != Some(CHECK) {
+        return Err(JsValue::from_str("Wrong passphrase"));
+    }
+    remember(&key).await
+}
+
+async fn lock() -> Result<(), JsValue> {
+    browser()
+        .storage()
+        .session()
+        .remove(&JsValue::from_str(HISTORY_KEY))
+        .await?;
+    Ok(())
+}
+
+async fn disable() -> Result<(), JsValue> {
+    let Some(key) = unlocked_key().await else {
+        return Err(JsValue::from_str("Unlock the history first"));
+    };
+    reseal(Some(&key), None).await?;
+    browser()
+        .storage()
+        .local()
+        .remove(&JsValue::from_str(HISTORY_VAULT))
+        .await?;
+    lock().await
+}
+
+async fn remember(key: &Key) -> Result<(), JsValue> {
+    let items = Object::new();
+    Reflect::set(
+        &items,
+        &JsValue::from_str(HISTORY_KEY),
+        &JsValue::from_str(&STANDARD.encode(key.0)),
+    )?;
+    browser().storage().session().set(&items).await?;
+    Ok(())
+}
+
+/// Rewrites every content and index record, opened with `from` and sealed with `to`.
+async fn reseal(from: Option<&Key>, to: Option<&Key>) -> Result<(), JsValue> {
+    let db = open().await?;
+    let tx =
+        db.transaction_on_multi_with_mode(&[INDEXES, CONTENTS], IdbTransactionMode::Readwrite)?;
+    if let Some(cursor) = tx.object_store(CONTENTS)?.open_cursor()?.await? {
+        loop {
+            let content = Record::from_js(cursor.value())?.open(from)?;
+            cursor.update(&Record::new(content, to)?.to_js()?)?.await?;
+            if !cursor.continue_cursor()?.await? {
+                break;
+            }
+        }
+    }
+    if let Some(cursor) = tx.object_store(INDEXES)?.open_cursor()?.await? {
+        loop {
+            let item = open_index(from_js(cursor.value())?, from)?;
+            cursor.update(&index_record(&item, to)?)?.await?;
+            if !cursor.continue_cursor()?.await? {
+                break;
+            }
+        }
+    }
+    tx.await.into_result()?;
+    Ok(())
+}
+
+async fn derive(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Key, JsValue> {
+    let Some(window) = web_sys::window() else {
+        return Err(JsValue::from_str("No window"));
+    };
+    let subtle = window.crypto()?.subtle();
+    let material: CryptoKey = JsFuture::from(subtle.import_key_with_str(
+        "raw",
+        &Uint8Array::from(passphrase.as_bytes()),
+        "PBKDF2",
+        false,
+        &Array::of1(&JsValue::from_str("deriveBits")),
+    )?)
+    .await?
+    .unchecked_into();
+    let params = Pbkdf2Params::new(
+        "PBKDF2",
+        &JsValue::from_str("SHA-256"),
+        iterations,
+        &Uint8Array::from(salt),
+    );
+    let bits: ArrayBuffer =
+        JsFuture::from(subtle.derive_bits_with_object(&params, &material, 256)?)
+            .await?
+            .unchecked_into();
+    let key = Uint8Array::new(&bits).to_vec();
+    key.try_into()
+        .map(Key)
+        .map_err(|_| JsValue::from_str("Derived key has the wrong length"))
+}
+
+fn random(buf: &mut [u8]) -> Result<(), JsValue> {
+    let Some(window) = web_sys::window() else {
+        return Err(JsValue::from_str("No window"));
+    };
+    window.crypto()?.get_random_values_with_u8_array(buf)?;
+    Ok(())
+}
+
+/// Turns history encryption on and off, and unlocks it for the browser session. `on_change`
+/// runs after the state changed.
+#[component]
+pub fn VaultPanel(
+    state: RwSignal<Option<VaultState>>,
+    on_change: impl Fn() + Copy + Send + Sync + 'static,
+) -> impl IntoView {
+    let passphrase = RwSignal::new(String::new());
+    let confirm = RwSignal::new(String::new());
+    let message = RwSignal::new(None::<String>);
+
+    let run = Action::new_local(move |step: &Step| {
+        let step = *step;
+        let (entered, repeated) = (passphrase.get_untracked(), confirm.get_untracked());
+        async move {
+            let result = match step {
+                Step::Enable if entered != repeated => {
+                    Err(JsValue::from_str("The passphrases differ"))
+                }
+                Step::Enable if entered.is_empty() => Err(JsValue::from_str("Enter a passphrase")),
+                Step::Enable => enable(&entered).await,
+                Step::Unlock => unlock(&entered).await,
+                Step::Lock => lock().await,
+                Step::Disable => disable().await,
+            };
+            match result {
+                Ok(()) => {
+                    message.set(None);
+                    passphrase.set(String::new());
+                    confirm.set(String::new());
+                }
+                Err(e) => {
+                    error!("Failed to change history encryption: {e:?}");
+                    message.set(Some(e.as_string().unwrap_or_else(|| format!("{e:?}"))));
+                }
+            }
+            state.set(Some(self::state().await));
+            on_change();
+        }
+    });
+    let busy = move || run.pending().get();
+
+    let passphrase_input = move |value: RwSignal<String>, placeholder: &'static str| {
+        view! {
+            <input
+                type="password"
+                class="input input-sm input-bordered"
+                placeholder=placeholder
+                prop:value=value
+                on:input=move |ev| value.set(event_target_value(&ev))
+            />
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-2 p-2">
+            {move || match state.get() {
+                None => ().into_any(),
+                Some(VaultState::Off) => {
+                    view! {
+                        <span class="text-sm">
+                            "Encrypt stored requests, responses, URLs and notes with a passphrase. It is asked once per browser session and can not be recovered. Methods, hosts, statuses and dates stay readable to list the history."
+                        </span>
+                        {passphrase_input(passphrase, "Passphrase")}
+                        {passphrase_input(confirm, "Repeat passphrase")}
+                        <button
+                            class="btn btn-sm btn-primary self-end"
+                            disabled=busy
+                            on:click=move |_| {
+                                run.dispatch(Step::Enable);
+                            }
+                        >
+                            Enable encryption
+                        </button>
+                    }
+                        .into_any()
+                }
+                Some(VaultState::Locked) => {
+                    view! {
+                        <span class="text-sm">"History is encrypted. Unlock it to open and save entries."</span>
+                        {passphrase_input(passphrase, "Passphrase")}
+                        <button
+                            class="btn btn-sm btn-primary self-end"
+                            disabled=busy
+                            on:click=move |_| {
+                                run.dispatch(Step::Unlock);
+                            }
+                        >
+                            Unlock
+                        </button>
+                    }
+                        .into_any()
+                }
+                Some(VaultState::Unlocked) => {
+                    view! {
+                        <span class="text-sm">"History is encrypted and unlocked for this browser session."</span>
+                        <div class="flex justify-end gap-2">
+                            <button
+                                class="btn btn-sm"
+                                disabled=busy
+                                on:click=move |_| {
+                                    run.dispatch(Step::Lock);
+                                }
+                            >
+                                Lock now
+                            </button>
+                            <button
+                                class="btn btn-sm btn-ghost"
+                                disabled=busy
+                                on:click=move |_| {
+                                    run.dispatch(Step::Disable);
+                                }
+                            >
+                                Disable encryption
+                            </button>
+                        </div>
+                    }
+                        .into_any()
+                }
+            }}
+            <Show when=move || busy()>
+                <span class="loading loading-dots loading-sm self-end"></span>
+            </Show>
+            {move || message.get().map(|message| view! { <span class="text-sm text-error">{message}</span> })}
+        </div>
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Enable,
+    Unlock,
+    Lock,
+    Disable,
+}